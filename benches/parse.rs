@@ -0,0 +1,104 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use filters::lexer::Lexer;
+use filters::parser::Parser;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Counting allocator.
+// Counts allocations (and reallocations) of the process.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// allocations made by f.
+fn allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let result = f();
+    let count = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(result);
+    count
+}
+
+// queries by name.
+fn queries() -> Vec<(&'static str, String)> {
+    let join = |items: Vec<String>, separator: &str| items.join(separator);
+    vec![
+        ("short", "name=jim".to_string()),
+        (
+            "long",
+            join((0..50).map(|i| format!("field{}>={}", i, i)).collect(), ","),
+        ),
+        (
+            "list",
+            format!(
+                "id=({})",
+                join((0..200).map(|i| i.to_string()).collect(), "|")
+            ),
+        ),
+        (
+            "quoted",
+            join(
+                (0..50)
+                    .map(|i| format!(r#"q{}='it\'s a "quoted" value, {}'"#, i, i))
+                    .collect(),
+                ",",
+            ),
+        ),
+    ]
+}
+
+fn bench_lex(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lex");
+    for (name, query) in queries() {
+        let count = allocations(|| Lexer::new(&query).count());
+        println!("lex/{}: {} allocations per scan", name, count);
+        group.throughput(Throughput::Bytes(query.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &query, |b, query| {
+            b.iter(|| Lexer::new(black_box(query)).count())
+        });
+    }
+    group.finish();
+}
+
+// Parsing keeps owned tokens (fields, values, list items and their
+// separators); each allocates its value, and its raw value only when it
+// has escapes. Example: parse/list is about one allocation per token.
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, query) in queries() {
+        let count = allocations(|| Parser::filter(&query).unwrap());
+        let tokens = Lexer::new(&query).count();
+        println!(
+            "parse/{}: {} allocations per parse ({} tokens)",
+            name, count, tokens
+        );
+        group.throughput(Throughput::Bytes(query.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &query, |b, query| {
+            b.iter(|| Parser::filter(black_box(query)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lex, bench_parse);
+criterion_main!(benches);
@@ -0,0 +1,18 @@
+#![no_main]
+
+use filters::lexer::Lexer;
+use libfuzzer_sys::fuzz_target;
+
+// Scanning never panics; tokens are in order and within the filter.
+fuzz_target!(|filter: &str| {
+    let mut end = 0;
+    for token in Lexer::new(filter) {
+        let Ok(token) = token else {
+            break;
+        };
+        assert!(end <= token.span.start && token.span.start < token.span.end);
+        assert!(filter.get(token.span.start..token.span.end).is_some());
+        end = token.span.end;
+    }
+    let _ = Lexer::tokens(filter);
+});
@@ -0,0 +1,38 @@
+#![no_main]
+
+use filters::filter::Filter;
+use filters::lexer::{Kind, Token};
+use filters::limits::Limits;
+use filters::operator::CompareOp;
+use filters::parser::Parser;
+use libfuzzer_sys::fuzz_target;
+
+// Shape field, operator and value kinds and values of a predicate.
+type Shape = (Kind, String, CompareOp, Vec<(Kind, String)>);
+
+// shape of the filter: kinds and values of the predicates, without spans.
+// Raw values may differ; strings are quoted from the value.
+fn shape(filter: &Filter) -> Vec<Shape> {
+    let token = |t: &Token| (t.kind, t.value.iter().collect::<String>());
+    filter
+        .predicates
+        .iter()
+        .map(|p| {
+            let (kind, field) = token(&p.field);
+            (kind, field, p.operator, p.value.0.iter().map(token).collect())
+        })
+        .collect()
+}
+
+// Parsing never panics; the canonical filter parses to the same
+// predicates and prints the same.
+// Expressions are parsed with limits as nesting is recursive.
+fuzz_target!(|filter: &str| {
+    let _ = Parser::expr_with(filter, &Limits::default());
+    if let Ok(parsed) = Parser::filter(filter) {
+        let query = parsed.to_string();
+        let reparsed = Parser::filter(&query).expect(&query);
+        assert_eq!(reparsed.to_string(), query);
+        assert_eq!(shape(&reparsed), shape(&parsed), "{}", query);
+    }
+});
@@ -0,0 +1,194 @@
+use crate::builder;
+use crate::error::Span;
+use crate::filter::Filter;
+use crate::lexer::{AND, Kind, Lexer, OR, Token, escape, quote};
+use crate::operator::CompareOp;
+use crate::parser::{Predicate, Value};
+use proptest::prelude::*;
+
+// Proptest strategies for filters.
+// Generated tokens are as the parser builds them from the canonical
+// filter (Display), with empty spans.
+
+// text of a token; special characters and timestamps are likely.
+pub fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z][a-z0-9_.]{0,8}",
+        "[ -~]{0,8}",
+        "\\PC{0,6}",
+        "-?[0-9]{1,4}(\\.[0-9]{1,3})?",
+        "20[0-9]{2}-0[1-9]-[0-2][1-9](T[0-2][0-9](:[0-5][0-9]){0,2})?",
+    ]
+}
+
+// token with the text as a literal, escaped literal or string.
+pub fn token() -> impl Strategy<Value = Token> {
+    (text(), 0..3).prop_map(|(text, form)| match form {
+        0 => builder::token(&text),
+        1 => escaped(&text).unwrap_or_else(|| builder::token(&text)),
+        _ => string(&text),
+    })
+}
+
+// field token; as token() for the field of a predicate.
+pub fn field() -> impl Strategy<Value = Token> {
+    (text(), 0..3).prop_map(|(text, form)| match form {
+        0 => builder::field_token(&text),
+        1 => escaped(&text).unwrap_or_else(|| builder::field_token(&text)),
+        _ => string(&text),
+    })
+}
+
+// escaped literal; None when the escaped text does not scan as one.
+fn escaped(text: &str) -> Option<Token> {
+    let value: Vec<char> = text.chars().collect();
+    match Lexer::tokens(&escape(&value)) {
+        Ok(mut tokens) if tokens.len() == 1 && tokens[0].kind == Kind::Literal => {
+            let mut token = tokens.remove(0);
+            token.span = Span::default();
+            Some(token)
+        }
+        _ => None,
+    }
+}
+
+// quoted string.
+fn string(text: &str) -> Token {
+    let value: Vec<char> = text.chars().collect();
+    let quoted: Vec<char> = quote(&value).chars().collect();
+    Token {
+        kind: Kind::String,
+        raw: Token::unless_value(quoted[1..quoted.len() - 1].to_vec(), &value),
+        value,
+        span: Span::default(),
+    }
+}
+
+// value; a single token or a list joined by `,` or `|`.
+pub fn value() -> impl Strategy<Value = Value> {
+    let list = (
+        prop::collection::vec(token(), 2..5),
+        prop_oneof![Just(AND), Just(OR)],
+    );
+    prop_oneof![
+        token().prop_map(|token| Value(vec![token])),
+        list.prop_map(|(tokens, separator)| {
+            let mut joined: Vec<Token> = vec![];
+            for (i, token) in tokens.into_iter().enumerate() {
+                if i > 0 {
+                    joined.push(Token {
+                        kind: Kind::Operator,
+                        value: vec![separator],
+                        raw: None,
+                        span: Span::default(),
+                    });
+                }
+                joined.push(token);
+            }
+            Value(joined)
+        }),
+    ]
+}
+
+pub fn predicate() -> impl Strategy<Value = Predicate> {
+    (
+        field(),
+        prop::sample::select(CompareOp::ALL.to_vec()),
+        value(),
+    )
+        .prop_map(|(field, operator, value)| Predicate {
+            field,
+            operator,
+            value,
+            span: Span::default(),
+        })
+}
+
+pub fn filter() -> impl Strategy<Value = Filter> {
+    prop::collection::vec(predicate(), 0..5).prop_map(|predicates| Filter { predicates })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::Limits;
+    use crate::parser::Parser;
+
+    // clear the spans of the filter.
+    fn unspanned(mut filter: Filter) -> Filter {
+        for p in filter.predicates.iter_mut() {
+            p.span = Span::default();
+            p.field.span = Span::default();
+            for token in p.value.0.iter_mut() {
+                token.span = Span::default();
+            }
+        }
+        filter
+    }
+
+    // Shape field, operator and value kinds and values of a predicate.
+    type Shape = (Kind, String, CompareOp, Vec<(Kind, String)>);
+
+    // shape of the filter: kinds and values of the predicates.
+    // Raw values may differ; strings are quoted from the value.
+    fn shape(filter: &Filter) -> Vec<Shape> {
+        let token = |t: &Token| (t.kind, t.value.iter().collect::<String>());
+        filter
+            .predicates
+            .iter()
+            .map(|p| {
+                let (kind, field) = token(&p.field);
+                (
+                    kind,
+                    field,
+                    p.operator,
+                    p.value.0.iter().map(token).collect(),
+                )
+            })
+            .collect()
+    }
+
+    // input likely to reach the parser.
+    fn input() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "[a-z0-9=!<>~:,|()'\"\\\\ ]{0,40}",
+            "\\PC{0,40}",
+            any::<String>(),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_filter_reparse(filter in filter()) {
+            let query = filter.to_string();
+            let parsed = Parser::filter(&query);
+            prop_assert!(parsed.is_ok(), "{}: {:?}", query, parsed);
+            prop_assert_eq!(unspanned(parsed.unwrap()), filter, "{}", query);
+        }
+
+        #[test]
+        fn test_lexer_spans(filter in input()) {
+            let mut end = 0;
+            for token in Lexer::new(&filter) {
+                let Ok(token) = token else {
+                    break;
+                };
+                prop_assert!(end <= token.span.start && token.span.start < token.span.end);
+                prop_assert!(filter.get(token.span.start..token.span.end).is_some());
+                end = token.span.end;
+            }
+        }
+
+        #[test]
+        fn test_parser_input(filter in input()) {
+            let _ = Parser::expr_with(&filter, &Limits::default());
+            if let Ok(parsed) = Parser::filter(&filter) {
+                let query = parsed.to_string();
+                let reparsed = Parser::filter(&query);
+                prop_assert!(reparsed.is_ok(), "{} => {}: {:?}", filter, query, reparsed);
+                prop_assert_eq!(reparsed.as_ref().unwrap().to_string(), query.clone());
+                prop_assert_eq!(shape(&reparsed.unwrap()), shape(&parsed), "{}", query);
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use crate::error::{FilterError, Span};
+use crate::filter::Filter;
+use crate::lexer::{AND, Kind, OR, Token, quote};
+use crate::operator::CompareOp;
+use crate::parser::{Predicate, Value};
+use std::fmt::Display;
+
+// FilterBuilder builds filters in code.
+// Builds the same predicates as Parser::filter(); values that would not
+// scan as a literal are strings (quoted when displayed).
+// Built tokens have an empty span as they are not part of a filter.
+// Example:
+//   FilterBuilder::new()
+//       .eq("name", "jim")
+//       .gt("age", 18)
+//       .any_of("label", ["a", "b"])
+//       .build()
+//   => name=jim,age>18,label=(a|b)
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FilterBuilder {
+    predicates: Vec<Predicate>,
+}
+
+impl FilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Predicate adds the field operator value predicate.
+    pub fn predicate(self, field: &str, operator: CompareOp, value: impl Display) -> Self {
+        let value = Value(vec![token(&value.to_string())]);
+        self.push(field, operator, value)
+    }
+
+    pub fn eq(self, field: &str, value: impl Display) -> Self {
+        self.predicate(field, CompareOp::Eq, value)
+    }
+
+    pub fn not_eq(self, field: &str, value: impl Display) -> Self {
+        self.predicate(field, CompareOp::NotEq, value)
+    }
+
+    pub fn lt(self, field: &str, value: impl Display) -> Self {
+        self.predicate(field, CompareOp::Lt, value)
+    }
+
+    pub fn le(self, field: &str, value: impl Display) -> Self {
+        self.predicate(field, CompareOp::Le, value)
+    }
+
+    pub fn gt(self, field: &str, value: impl Display) -> Self {
+        self.predicate(field, CompareOp::Gt, value)
+    }
+
+    pub fn ge(self, field: &str, value: impl Display) -> Self {
+        self.predicate(field, CompareOp::Ge, value)
+    }
+
+    // Like adds the (~) predicate; (*) is the wildcard.
+    pub fn like(self, field: &str, value: impl Display) -> Self {
+        self.predicate(field, CompareOp::Like, value)
+    }
+
+    pub fn not_like(self, field: &str, value: impl Display) -> Self {
+        self.predicate(field, CompareOp::NotLike, value)
+    }
+
+    pub fn has(self, field: &str, value: impl Display) -> Self {
+        self.predicate(field, CompareOp::Has, value)
+    }
+
+    // AnyOf adds field=(a|b).
+    pub fn any_of<V: Display>(self, field: &str, values: impl IntoIterator<Item = V>) -> Self {
+        self.list(field, CompareOp::Eq, OR, values)
+    }
+
+    // AllOf adds field=(a,b).
+    pub fn all_of<V: Display>(self, field: &str, values: impl IntoIterator<Item = V>) -> Self {
+        self.list(field, CompareOp::Eq, AND, values)
+    }
+
+    // NoneOf adds field!=(a|b).
+    pub fn none_of<V: Display>(self, field: &str, values: impl IntoIterator<Item = V>) -> Self {
+        self.list(field, CompareOp::NotEq, OR, values)
+    }
+
+    // List adds the predicate with values joined by the separator (`,` `|`).
+    pub fn list<V: Display>(
+        self,
+        field: &str,
+        operator: CompareOp,
+        separator: char,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        let mut tokens: Vec<Token> = vec![];
+        for (i, v) in values.into_iter().enumerate() {
+            if i > 0 {
+                tokens.push(Token {
+                    kind: Kind::Operator,
+                    value: vec![separator],
+                    raw: None,
+                    span: Span::default(),
+                });
+            }
+            tokens.push(token(&v.to_string()));
+        }
+        self.push(field, operator, Value(tokens))
+    }
+
+    // Merge adds the predicates of the (parsed) filter.
+    pub fn merge(mut self, filter: &Filter) -> Self {
+        self.predicates.extend(filter.predicates.iter().cloned());
+        self
+    }
+
+    // Build returns the filter.
+    // Returns EmptyList when a list has no values.
+    pub fn build(self) -> Result<Filter, FilterError> {
+        for p in self.predicates.iter() {
+            if p.value.0.is_empty() {
+                return Err(FilterError::EmptyList { span: p.span });
+            }
+        }
+        Ok(Filter {
+            predicates: self.predicates,
+        })
+    }
+
+    // push the predicate.
+    fn push(mut self, field: &str, operator: CompareOp, value: Value) -> Self {
+        self.predicates.push(Predicate {
+            field: field_token(field),
+            operator,
+            value,
+            span: Span::default(),
+        });
+        self
+    }
+}
+
+impl From<Filter> for FilterBuilder {
+    fn from(filter: Filter) -> Self {
+        FilterBuilder {
+            predicates: filter.predicates,
+        }
+    }
+}
+
+// token for the value.
+// A literal when it scans as one, else a string.
+pub(crate) fn token(value: &str) -> Token {
+    scanned(value, false)
+}
+
+// field_token for the field name.
+// A literal when it scans as one in a field, else a string.
+pub(crate) fn field_token(name: &str) -> Token {
+    scanned(name, true)
+}
+
+// scanned token for the text in a field or a value.
+fn scanned(text: &str, field: bool) -> Token {
+    let value: Vec<char> = text.chars().collect();
+    if Token::bare(text, field) {
+        Token {
+            kind: Kind::Literal,
+            raw: None,
+            value,
+            span: Span::default(),
+        }
+    } else {
+        // raw is the quoted value without the quotes.
+        let quoted: Vec<char> = quote(&value).chars().collect();
+        Token {
+            kind: Kind::String,
+            raw: Token::unless_value(quoted[1..quoted.len() - 1].to_vec(), &value),
+            value,
+            span: Span::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datetime::Date;
+    use crate::lexer::TokenValue;
+    use crate::parser::Parser;
+
+    // Shape predicate without spans.
+    type Shape = (String, CompareOp, Vec<(Kind, String)>);
+
+    // shape of the filter.
+    fn shape(filter: &Filter) -> Vec<Shape> {
+        filter
+            .predicates
+            .iter()
+            .map(|p| {
+                let value = p.value.0.iter().map(|t| (t.kind, t.to_string())).collect();
+                (p.field.to_string(), p.operator, value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_builder() {
+        let filter = FilterBuilder::new()
+            .eq("name", "jim")
+            .gt("age", 18)
+            .any_of("label", ["a", "b"])
+            .all_of("tags", vec!["x".to_string(), "y".to_string()])
+            .none_of("status", ["closed"])
+            .like("title", "crate*")
+            .le("created", Date::parse("2024-01-31").unwrap())
+            .build()
+            .unwrap();
+        let query = "name=jim,age>18,label=(a|b),tags=(x,y),status!=(closed),title~crate*,created<=2024-01-31";
+        assert_eq!(shape(&filter), shape(&Parser::filter(query).unwrap()));
+        assert_eq!(
+            filter.to_string(),
+            "name=jim,age>18,label=(a|b),tags=(x,y),status!=closed,title~crate*,created<=2024-01-31"
+        );
+        assert_eq!(
+            filter.field("age").unwrap().value().0[0].as_value(),
+            TokenValue::Int(18)
+        );
+    }
+
+    #[test]
+    fn test_builder_quoting() {
+        let filter = FilterBuilder::new()
+            .eq("address", "1 Main st, apt=2")
+            .any_of("label", ["part-of: x", "it's"])
+            .eq("first name", "")
+            .build()
+            .unwrap();
+        let query = filter.to_string();
+        assert_eq!(
+            query,
+            "address='1 Main st, apt=2',label=('part-of: x'|\"it's\"),'first name'=''"
+        );
+        let p = filter.field("address").unwrap();
+        assert_eq!(p.value().0[0].kind, Kind::String);
+    }
+
+    #[test]
+    fn test_builder_merge() {
+        let user = Parser::filter("name~jim*,age>18").unwrap();
+        let filter = FilterBuilder::from(user)
+            .eq("tenant", "acme")
+            .build()
+            .unwrap();
+        assert_eq!(filter.to_string(), "name~jim*,age>18,tenant=acme");
+        assert_eq!(filter.predicates[0].span, Span::new(0, 9));
+
+        let user = Parser::filter("name=jim").unwrap();
+        let filter = FilterBuilder::new()
+            .eq("tenant", "acme")
+            .merge(&user)
+            .build()
+            .unwrap();
+        assert_eq!(filter.to_string(), "tenant=acme,name=jim");
+    }
+
+    #[test]
+    fn test_builder_empty_list() {
+        let empty: [&str; 0] = [];
+        let filter = FilterBuilder::new().any_of("tenant", empty).build();
+        assert_eq!(
+            filter,
+            Err(FilterError::EmptyList {
+                span: Span::default()
+            })
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+// Date calendar date.
+// Format: YYYY-MM-DD
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    // Parse the date.
+    // Returns None when not a valid date.
+    pub fn parse(s: &str) -> Option<Date> {
+        let b = s.as_bytes();
+        if b.len() != 10 || b[4] != b'-' || b[7] != b'-' {
+            return None;
+        }
+        let date = Date {
+            year: number(&s[0..4])? as i32,
+            month: number(&s[5..7])?,
+            day: number(&s[8..10])?,
+        };
+        let valid = (1..=12).contains(&date.month)
+            && date.day >= 1
+            && date.day <= days_in_month(date.year, date.month);
+        valid.then_some(date)
+    }
+
+    // Days returns the days since 1970-01-01.
+    pub fn days(&self) -> i64 {
+        // days_from_civil (H. Hinnant).
+        let y = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let m = self.month as i64;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+// DateTime RFC 3339 date and time.
+// Format: YYYY-MM-DDTHH:MM:SS[.fraction](Z|+HH:MM|-HH:MM)
+// Compared as instants; the offset is kept for display.
+#[derive(Debug, Clone, Copy)]
+pub struct DateTime {
+    pub date: Date,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanos: u32,
+    // Minutes east of UTC.
+    pub offset: i32,
+}
+
+impl DateTime {
+    // Parse the date and time.
+    // Returns None when not a valid RFC 3339 date and time.
+    pub fn parse(s: &str) -> Option<DateTime> {
+        if s.len() < 20 || !s.is_char_boundary(10) || !s.is_char_boundary(19) {
+            return None;
+        }
+        let date = Date::parse(&s[0..10])?;
+        let b = s.as_bytes();
+        if !matches!(b[10], b'T' | b't') || b[13] != b':' || b[16] != b':' {
+            return None;
+        }
+        let hour = number(&s[11..13])?;
+        let minute = number(&s[14..16])?;
+        let second = number(&s[17..19])?;
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+
+        let mut rest = &s[19..];
+        let mut nanos = 0;
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let len = fraction.bytes().take_while(|b| b.is_ascii_digit()).count();
+            if len == 0 || len > 9 {
+                return None;
+            }
+            nanos = number(&fraction[..len])? * 10u32.pow(9 - len as u32);
+            rest = &fraction[len..];
+        }
+
+        let offset = match rest.as_bytes() {
+            [b'Z' | b'z'] => 0,
+            [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+                let hours = number(&rest[1..3])?;
+                let minutes = number(&rest[4..6])?;
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                let offset = (hours * 60 + minutes) as i32;
+                if *sign == b'-' { -offset } else { offset }
+            }
+            _ => return None,
+        };
+
+        Some(DateTime {
+            date,
+            hour,
+            minute,
+            second,
+            nanos,
+            offset,
+        })
+    }
+
+    // Timestamp returns seconds since the UNIX epoch (UTC) and nanoseconds.
+    pub fn timestamp(&self) -> (i64, u32) {
+        let seconds = self.date.days() * 86400
+            + (self.hour * 3600 + self.minute * 60 + self.second) as i64
+            - self.offset as i64 * 60;
+        (seconds, self.nanos)
+    }
+}
+
+impl PartialEq for DateTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.timestamp() == other.timestamp()
+    }
+}
+
+impl Eq for DateTime {}
+
+impl Ord for DateTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.timestamp().cmp(&other.timestamp())
+    }
+}
+
+impl PartialOrd for DateTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for DateTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}",
+            self.date, self.hour, self.minute, self.second
+        )?;
+        if self.nanos > 0 {
+            let fraction = format!("{:09}", self.nanos);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        if self.offset == 0 {
+            write!(f, "Z")
+        } else {
+            let sign = if self.offset < 0 { '-' } else { '+' };
+            let offset = self.offset.abs();
+            write!(f, "{}{:02}:{:02}", sign, offset / 60, offset % 60)
+        }
+    }
+}
+
+// Duration relative amount of time.
+// Format: one or more <number><unit>; units: w d h m s.
+// Example: 7d, 1h30m
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Duration {
+    pub seconds: u64,
+}
+
+impl Duration {
+    // Parse the duration.
+    // Returns None when not a duration.
+    pub fn parse(s: &str) -> Option<Duration> {
+        if s.is_empty() {
+            return None;
+        }
+        let mut seconds: u64 = 0;
+        let mut n: Option<u64> = None;
+        for ch in s.chars() {
+            match ch {
+                '0'..='9' => {
+                    let digit = ch as u64 - '0' as u64;
+                    n = Some(n.unwrap_or(0).checked_mul(10)?.checked_add(digit)?);
+                }
+                'w' | 'd' | 'h' | 'm' | 's' => {
+                    let unit = match ch {
+                        'w' => 604800,
+                        'd' => 86400,
+                        'h' => 3600,
+                        'm' => 60,
+                        _ => 1,
+                    };
+                    seconds = seconds.checked_add(n?.checked_mul(unit)?)?;
+                    n = None;
+                }
+                _ => return None,
+            }
+        }
+        match n {
+            Some(_) => None,
+            None => Some(Duration { seconds }),
+        }
+    }
+
+    pub fn to_std(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.seconds)
+    }
+}
+
+impl Display for Duration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.seconds == 0 {
+            return write!(f, "0s");
+        }
+        let mut seconds = self.seconds;
+        for (unit, len) in [('d', 86400), ('h', 3600), ('m', 60), ('s', 1)] {
+            if seconds >= len {
+                write!(f, "{}{}", seconds / len, unit)?;
+                seconds %= len;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Timestamp returns true when the text starts a date and time.
+// Used by the lexer to keep (:) in the time part of a value.
+// Example: 2024-05-01T10
+pub fn timestamp(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 13
+        && bytes[..13].iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            10 => matches!(b, b'T' | b't'),
+            _ => b.is_ascii_digit(),
+        })
+        && bytes[13..]
+            .iter()
+            .all(|b| b.is_ascii_digit() || matches!(b, b':' | b'.' | b'+' | b'-'))
+}
+
+// number parses ASCII digits only.
+fn number(s: &str) -> Option<u32> {
+    if s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date() {
+        assert_eq!(
+            Date::parse("2024-02-29"),
+            Some(Date {
+                year: 2024,
+                month: 2,
+                day: 29
+            })
+        );
+        assert_eq!(Date::parse("2023-02-29"), None);
+        assert_eq!(Date::parse("2024-13-01"), None);
+        assert_eq!(Date::parse("2024-1-01"), None);
+        assert_eq!(Date::parse("+024-01-01"), None);
+        assert_eq!(Date::parse("yesterday"), None);
+        assert_eq!(Date::parse("1970-01-01").unwrap().days(), 0);
+        assert_eq!(Date::parse("2000-03-01").unwrap().days(), 11017);
+        assert_eq!(Date::parse("1969-12-31").unwrap().days(), -1);
+        assert!(Date::parse("2024-01-31") < Date::parse("2024-02-01"));
+    }
+
+    #[test]
+    fn test_datetime() {
+        let dt = DateTime::parse("2024-05-01T10:00:00Z").unwrap();
+        assert_eq!(dt.timestamp(), (1714557600, 0));
+        assert_eq!(dt.to_string(), "2024-05-01T10:00:00Z");
+
+        let dt = DateTime::parse("2024-05-01T15:30:00.250+05:30").unwrap();
+        assert_eq!(dt.timestamp(), (1714557600, 250_000_000));
+        assert_eq!(dt.to_string(), "2024-05-01T15:30:00.25+05:30");
+        assert!(dt > DateTime::parse("2024-05-01T10:00:00Z").unwrap());
+        assert_eq!(
+            DateTime::parse("2024-05-01T05:00:00-05:00"),
+            DateTime::parse("2024-05-01T10:00:00z")
+        );
+
+        assert_eq!(DateTime::parse("2024-05-01T10:00:00"), None);
+        assert_eq!(DateTime::parse("2024-05-01T24:00:00Z"), None);
+        assert_eq!(DateTime::parse("2024-05-01T10:00:00.Z"), None);
+        assert_eq!(DateTime::parse("2024-05-01 10:00:00Z"), None);
+        assert_eq!(DateTime::parse("2024-05-01T10:00:00+0530"), None);
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(Duration::parse("7d"), Some(Duration { seconds: 604800 }));
+        assert_eq!(Duration::parse("1h30m"), Some(Duration { seconds: 5400 }));
+        assert_eq!(Duration::parse("1w").unwrap().to_string(), "7d");
+        assert_eq!(Duration::parse("90s").unwrap().to_string(), "1m30s");
+        assert_eq!(Duration::parse("0s").unwrap().to_string(), "0s");
+        assert_eq!(Duration::parse("7"), None);
+        assert_eq!(Duration::parse("d"), None);
+        assert_eq!(Duration::parse("7y"), None);
+        assert_eq!(Duration::parse(""), None);
+        assert_eq!(Duration::parse("99999999999999999999d"), None);
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert!(timestamp("2024-05-01T10"));
+        assert!(timestamp("2024-05-01T10:00:00+05"));
+        assert!(!timestamp("2024-05-01"));
+        assert!(!timestamp("name"));
+        assert!(!timestamp("2024-05-01T10:00:00Z"));
+        assert!(!timestamp("2024-05-01T1é"));
+    }
+}
@@ -0,0 +1,149 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+// Decimal arbitrary-precision decimal number.
+// Stored normalized as sign, digits and scale.
+// Example: -12.50 => (negative, "125", 1)
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Decimal {
+    negative: bool,
+    digits: String,
+    scale: usize,
+}
+
+impl Decimal {
+    // Parse the decimal.
+    // Format: [+-]digits[.digits]
+    // Returns None when not a decimal.
+    pub fn parse(s: &str) -> Option<Decimal> {
+        let (negative, s) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (int, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if int.is_empty() && fraction.is_empty() || !digits(int) || !digits(fraction) {
+            return None;
+        }
+
+        let fraction = fraction.trim_end_matches('0');
+        let digits = format!("{}{}", int, fraction);
+        let digits = digits.trim_start_matches('0');
+        if digits.is_empty() {
+            return Some(Decimal::zero());
+        }
+        Some(Decimal {
+            negative,
+            digits: digits.to_string(),
+            scale: fraction.len(),
+        })
+    }
+
+    pub fn zero() -> Decimal {
+        Decimal {
+            negative: false,
+            digits: "0".to_string(),
+            scale: 0,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits == "0"
+    }
+
+    // Scale returns the number of fraction digits.
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    // ToF64 returns the nearest f64.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    // magnitude digits padded to the scale without leading zeros.
+    fn magnitude(&self, scale: usize) -> String {
+        let mut digits = self.digits.clone();
+        digits.push_str(&"0".repeat(scale - self.scale));
+        digits.trim_start_matches('0').to_string()
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => return Ordering::Greater,
+            (true, false) => return Ordering::Less,
+            _ => {}
+        }
+        let scale = self.scale.max(other.scale);
+        let (a, b) = (self.magnitude(scale), other.magnitude(scale));
+        let ordering = a.len().cmp(&b.len()).then_with(|| a.cmp(&b));
+        if self.negative {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+        if self.scale == 0 {
+            return write!(f, "{}", self.digits);
+        }
+        let digits = format!("{:0>width$}", self.digits, width = self.scale + 1);
+        let (int, fraction) = digits.split_at(digits.len() - self.scale);
+        write!(f, "{}.{}", int, fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_decimal_parse() {
+        assert_eq!(d("-12.50").to_string(), "-12.5");
+        assert_eq!(d("+007").to_string(), "7");
+        assert_eq!(d(".05").to_string(), "0.05");
+        assert_eq!(d("-0.00").to_string(), "0");
+        assert_eq!(d("5.").to_string(), "5");
+        assert_eq!(
+            d("123456789012345678901234567890.000000000000000000001").to_string(),
+            "123456789012345678901234567890.000000000000000000001"
+        );
+        assert_eq!(d("-0.00"), Decimal::zero());
+        assert_eq!(Decimal::parse(""), None);
+        assert_eq!(Decimal::parse("-"), None);
+        assert_eq!(Decimal::parse("."), None);
+        assert_eq!(Decimal::parse("1e5"), None);
+        assert_eq!(Decimal::parse("1.2.3"), None);
+        assert_eq!(Decimal::parse("inf"), None);
+    }
+
+    #[test]
+    fn test_decimal_ord() {
+        assert!(d("0.5") > d("0"));
+        assert!(d("-0.5") < d("0"));
+        assert!(d("10") > d("9.99999"));
+        assert!(d("-10") < d("-9.99999"));
+        assert!(d("1.10") == d("1.1"));
+        assert!(d("99999999999999999999999") > d("99999999999999999999998.9"));
+        assert_eq!(d("2.5").to_f64(), 2.5);
+    }
+}
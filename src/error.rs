@@ -0,0 +1,355 @@
+use crate::limits::Limit;
+use crate::operator::CompareOp;
+use std::fmt::{Display, Formatter};
+
+// Span byte offsets [start, end) in the filter.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    // Text returns the spanned text of the filter.
+    // Returns "" when the span is not within the filter.
+    pub fn text<'a>(&self, filter: &'a str) -> &'a str {
+        filter.get(self.start..self.end).unwrap_or_default()
+    }
+
+    // Join returns the span covering both spans.
+    pub fn join(&self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+// FilterError reported by the lexer and parser.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FilterError {
+    // Quoted string without the closing quote.
+    UnterminatedQuote {
+        quote: char,
+        span: Span,
+    },
+    // Escape sequence not valid in a literal or quoted string. Example: \u{D800}
+    InvalidEscape {
+        sequence: String,
+        span: Span,
+    },
+    // Percent-encoding not valid. Example: %zz
+    InvalidEncoding {
+        sequence: String,
+        span: Span,
+    },
+    // Operator not valid in this position.
+    UnknownOperator {
+        operator: String,
+        span: Span,
+    },
+    // List or group without the closing `)`.
+    UnterminatedList {
+        span: Span,
+    },
+    // List without items. Example: ()
+    EmptyList {
+        span: Span,
+    },
+    // List mixing `,` and `|`. Example: (a|b,c)
+    MixedListOperators {
+        expected: char,
+        found: char,
+        span: Span,
+    },
+    // Group mixing `,` and `|`. Example: a=1|b=2,c=3
+    MixedOperators {
+        expected: char,
+        found: char,
+        span: Span,
+    },
+    // Token not valid in this position.
+    UnexpectedToken {
+        found: String,
+        span: Span,
+    },
+    // Field name not valid for the backend.
+    InvalidField {
+        name: String,
+        span: Span,
+    },
+    // Field not declared in the schema or mapping.
+    UnknownField {
+        name: String,
+        suggestion: Option<String>,
+        span: Span,
+    },
+    // Operator not supported by the field.
+    UnsupportedOperator {
+        field: String,
+        operator: CompareOp,
+        span: Span,
+    },
+    // Value not valid for the field type.
+    InvalidValue {
+        field: String,
+        value: String,
+        expected: String,
+        span: Span,
+    },
+    // Input ended before the construct was complete.
+    UnexpectedEnd {
+        span: Span,
+    },
+    // Filter exceeding a parse limit. See: Limits.
+    LimitExceeded {
+        limit: Limit,
+        max: usize,
+        span: Span,
+    },
+}
+
+impl FilterError {
+    // Span returns the offending range in the filter.
+    pub fn span(&self) -> Span {
+        match self {
+            FilterError::UnterminatedQuote { span, .. }
+            | FilterError::InvalidEscape { span, .. }
+            | FilterError::InvalidEncoding { span, .. }
+            | FilterError::UnknownOperator { span, .. }
+            | FilterError::UnterminatedList { span }
+            | FilterError::EmptyList { span }
+            | FilterError::MixedListOperators { span, .. }
+            | FilterError::MixedOperators { span, .. }
+            | FilterError::UnexpectedToken { span, .. }
+            | FilterError::InvalidField { span, .. }
+            | FilterError::UnknownField { span, .. }
+            | FilterError::UnsupportedOperator { span, .. }
+            | FilterError::InvalidValue { span, .. }
+            | FilterError::UnexpectedEnd { span }
+            | FilterError::LimitExceeded { span, .. } => *span,
+        }
+    }
+}
+
+impl FilterError {
+    // Message returns the explanation without the position.
+    pub fn message(&self) -> String {
+        match self {
+            FilterError::UnterminatedQuote { quote, .. } => format!("end {} not found", quote),
+            FilterError::InvalidEscape { sequence, .. } => {
+                format!("invalid escape `{}`", sequence)
+            }
+            FilterError::InvalidEncoding { sequence, .. } => {
+                format!("invalid percent-encoding `{}`", sequence)
+            }
+            FilterError::UnknownOperator { operator, .. } => {
+                format!("unknown operator `{}`", operator)
+            }
+            FilterError::UnterminatedList { .. } => "end ')' not found".to_string(),
+            FilterError::EmptyList { .. } => "list cannot be empty".to_string(),
+            FilterError::MixedListOperators {
+                expected, found, ..
+            } => format!("mixed operators `{}` and `{}` in list", expected, found),
+            FilterError::MixedOperators {
+                expected, found, ..
+            } => format!(
+                "mixed operators `{}` and `{}`, use () to group",
+                expected, found
+            ),
+            FilterError::UnexpectedToken { found, .. } => format!("unexpected `{}`", found),
+            FilterError::InvalidField { name, .. } => format!("invalid field `{}`", name),
+            FilterError::UnknownField {
+                name, suggestion, ..
+            } => match suggestion {
+                Some(suggestion) => {
+                    format!("unknown field `{}`, did you mean `{}`?", name, suggestion)
+                }
+                None => format!("unknown field `{}`", name),
+            },
+            FilterError::UnsupportedOperator {
+                field, operator, ..
+            } => format!("operator `{}` not supported by field `{}`", operator, field),
+            FilterError::InvalidValue {
+                field,
+                value,
+                expected,
+                ..
+            } => format!(
+                "invalid value `{}` for field `{}`, expected {}",
+                value, field, expected
+            ),
+            FilterError::UnexpectedEnd { .. } => "unexpected end of filter".to_string(),
+            FilterError::LimitExceeded { limit, max, .. } => {
+                format!("{} exceeds the limit of {}", limit, max)
+            }
+        }
+    }
+
+    // Render returns the filter with the offending range underlined.
+    // Example:
+    //   cat=(one|two,three)
+    //               ^ mixed operators `|` and `,` in list
+    pub fn render(&self, filter: &str) -> String {
+        let span = self.span();
+        let start = filter.get(..span.start).map_or(0, |s| s.chars().count());
+        let width = span.text(filter).chars().count().max(1);
+        format!(
+            "{}\n{}{} {}",
+            filter,
+            " ".repeat(start),
+            "^".repeat(width),
+            self.message()
+        )
+    }
+}
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at {})", self.message(), self.span())
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_error_unterminated_quote() {
+        let p = Parser::filter("name='elmer");
+        assert_eq!(
+            p,
+            Err(FilterError::UnterminatedQuote {
+                quote: '\'',
+                span: Span::new(5, 11)
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_invalid_escape() {
+        let p = Parser::filter(r"path='C:\u{D800}x'");
+        let err = p.unwrap_err();
+        assert_eq!(
+            err,
+            FilterError::InvalidEscape {
+                sequence: r"\u{D800}".to_string(),
+                span: Span::new(8, 16)
+            }
+        );
+        assert_eq!(
+            err.render(r"path='C:\u{D800}x'"),
+            "path='C:\\u{D800}x'\n        ^^^^^^^^ invalid escape `\\u{D800}`"
+        );
+    }
+
+    #[test]
+    fn test_error_empty_list() {
+        let p = Parser::filter("name=elmer,cat=()");
+        assert_eq!(
+            p,
+            Err(FilterError::EmptyList {
+                span: Span::new(15, 17)
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_mixed_list_operators() {
+        let p = Parser::filter("cat=(one|two,three)");
+        assert_eq!(
+            p,
+            Err(FilterError::MixedListOperators {
+                expected: '|',
+                found: ',',
+                span: Span::new(12, 13)
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_unknown_list_operator() {
+        let p = Parser::filter("cat=(one=two)");
+        assert_eq!(
+            p,
+            Err(FilterError::UnknownOperator {
+                operator: "=".to_string(),
+                span: Span::new(8, 9)
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_unterminated_list() {
+        let p = Parser::filter("cat=(one|two");
+        assert_eq!(
+            p,
+            Err(FilterError::UnterminatedList {
+                span: Span::new(4, 12)
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_unexpected_token() {
+        let p = Parser::filter("name elmer=fudd");
+        assert_eq!(
+            p,
+            Err(FilterError::UnexpectedToken {
+                found: "elmer".to_string(),
+                span: Span::new(5, 10)
+            })
+        );
+    }
+
+    #[test]
+    fn test_span_text() {
+        let filter = "name=elmer,cat=()";
+        let err = Parser::filter(filter).unwrap_err();
+        assert_eq!(err.span().text(filter), "()");
+        assert_eq!(Span::new(15, 40).text(filter), "");
+    }
+
+    #[test]
+    fn test_error_render() {
+        let filter = "cat=(one|two,three)";
+        let err = Parser::filter(filter).unwrap_err();
+        assert_eq!(
+            err.render(filter),
+            "cat=(one|two,three)\n            ^ mixed operators `|` and `,` in list"
+        );
+
+        let filter = "name='élmer, age>1";
+        let err = Parser::filter(filter).unwrap_err();
+        assert_eq!(
+            err.render(filter),
+            "name='élmer, age>1\n     ^^^^^^^^^^^^^ end ' not found"
+        );
+
+        let filter = "name";
+        let err = Parser::filter(filter).unwrap_err();
+        assert_eq!(err.render(filter), "name\n    ^ unexpected end of filter");
+    }
+
+    #[test]
+    fn test_error_display() {
+        let err = FilterError::EmptyList {
+            span: Span::new(4, 6),
+        };
+        assert_eq!(err.to_string(), "list cannot be empty (at 4..6)");
+    }
+}
@@ -1,31 +1,32 @@
-use crate::lexer::Token;
-use crate::parser::{Predicate, Value};
+use crate::error::Span;
+use crate::lexer::{AND, ESCAPE, Kind, Token, unescape};
+use crate::operator::CompareOp;
+use crate::parser::{Expr, Predicate, Value};
+use std::fmt::{Display, Formatter};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Filter {
     pub predicates: Vec<Predicate>,
 }
 
 impl Filter {
-    // Field returns a field.
-    pub fn field(&self, name: &str) -> Option<Field> {
-        let fields = self.fields(name);
-        let field = fields.first();
-        field.cloned()
+    // Field returns the first field with the name.
+    pub fn field(&self, name: &str) -> Option<Field<'_>> {
+        self.fields(name).into_iter().next()
     }
 
     // Fields returns fields.
-    pub fn fields(&self, name: &str) -> Vec<Field> {
+    // Fields borrow the predicates of the filter.
+    pub fn fields(&self, name: &str) -> Vec<Field<'_>> {
         let mut fields: Vec<Field> = vec![];
 
         let name = name.to_lowercase();
         for p in self.predicates.iter() {
             let predicate_name: String = p.field.value.iter().collect();
             if predicate_name.to_lowercase() == name {
-                let f = Field {
-                    predicate: p.clone(),
-                };
-                fields.push(f);
+                fields.push(Field { predicate: p });
             }
         }
 
@@ -37,36 +38,60 @@ impl Filter {
         let r = r.to_lowercase();
         let mut predicates: Vec<Predicate> = vec![];
         for p in self.predicates.iter() {
-            let field = Field {
-                predicate: p.clone(),
-            };
-            if let Some(fr) = &field.resource() {
-                if fr.to_lowercase() == r {
-                    let field_name = field.name().chars().collect::<Vec<char>>();
-                    predicates.push(Predicate {
-                        field: Token {
-                            kind: p.field.kind.clone(),
-                            value: field_name,
-                        },
-                        ..p.clone()
-                    });
-                }
+            let field = Field { predicate: p };
+            let (resource, raw) = field.split_raw();
+            if let Some(fr) = resource
+                && fr.iter().collect::<String>().to_lowercase() == r
+            {
+                let value: Vec<char> = field.name().chars().collect();
+                predicates.push(Predicate {
+                    field: Token {
+                        kind: p.field.kind,
+                        raw: Token::unless_value(raw, &value),
+                        value,
+                        span: p.field.span,
+                    },
+                    operator: p.operator,
+                    value: p.value.clone(),
+                    span: p.span,
+                });
             }
         }
         Filter { predicates }
     }
 
+    // Expr returns the filter as an expression.
+    pub fn expr(&self) -> Expr {
+        let predicates = self.predicates.iter().cloned().map(Expr::Predicate);
+        Expr::And(predicates.collect())
+    }
+
     pub fn is_empty(&self) -> bool {
         self.predicates.is_empty()
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Field {
-    pub predicate: Predicate,
+// Display the canonical filter.
+// Parser::filter() of the result builds the same filter.
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, p) in self.predicates.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", AND)?;
+            }
+            write!(f, "{}", p)?;
+        }
+        Ok(())
+    }
+}
+
+// Field predicate of a filter.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Field<'a> {
+    pub predicate: &'a Predicate,
 }
 
-impl Field {
+impl<'a> Field<'a> {
     // Name returns the field name.
     pub fn name(&self) -> String {
         let (_, s) = self.split();
@@ -79,98 +104,90 @@ impl Field {
         s
     }
 
-    pub fn value(&self) -> Value {
-        let value = &self.predicate.value;
-        value.clone()
+    pub fn value(&self) -> &'a Value {
+        &self.predicate.value
+    }
+
+    pub fn operator(&self) -> CompareOp {
+        self.predicate.operator
     }
 
-    pub fn operator(&self) -> Token {
-        let token = &self.predicate.operator;
-        token.clone()
+    // Negated returns true when the operator is negated (`!=` `!~`).
+    // See: Predicate.
+    pub fn negated(&self) -> bool {
+        self.predicate.negated()
     }
 
-    // SQL builds SQL.
-    // Returns statement and values (for ?).
-    // pub fn sql(&self) {
-    //     let name = self.name();
-    //     let r = match self.predicate.value.0.len() {
-    //         0 => None,
-    //         1 => {
-    //             let operator_value: String = self.predicate.operator.value.iter().collect();
-    //             if operator_value == LIKE.to_string() {
-    //                 if let Some(token) = self.predicate.value.0.get(0) {
-    //                     let token_value: String = token.value.iter().collect();
-    //                     let v = token_value.replace("*", "%");
-    //                     let v_list = vec![TokenValue::String(v)];
-    //                     let s = format!("{}{}{}{}", name, &self.operator(), "?", " ");
-    //                     Some((s, v_list))
-    //                 } else {
-    //                     None
-    //                 }
-    //             } else {
-    //                 if let Some(token) = self.predicate.value.0.get(0) {
-    //                     let v_list = vec![token.as_value()];
-    //                     let s = format!("{}{}{}{}", name, &self.operator(), "?", " ");
-    //                     Some((s, v_list))
-    //                 } else {
-    //                     None
-    //                 }
-    //             }
-    //         }
-    //         _ => {
-    //             // if f.Value.Operator(AND) {
-    //             //     // not supported.
-    //             //     break
-    //             // }
-    //             self.predicate.operator.value
-    //             None
-    //         },
-    //     };
-    // }
+    // Span returns the range of the predicate in the filter.
+    pub fn span(&self) -> Span {
+        self.predicate.span
+    }
 
     // split field name.
     // format: resource.name
     // The resource may be "" (anonymous).
     // The (.) separator is escaped when preceded by (\).
     pub fn split(&self) -> (Option<String>, String) {
-        let s = &self.predicate.field.value;
-        let mark = s.iter().position(|c| *c == '.');
-        if let Some(mark) = mark {
-            if mark > 0 && s.get(mark - 1) == Some('\\').as_ref() {
-                let name: String = [&s[0..mark - 1], &s[mark..]].concat().iter().collect();
-                (None, name)
-            } else {
-                let relation: String = s[0..mark].iter().collect();
-                let name: String = s[mark + 1..].iter().collect();
-                (Some(relation), name)
-            }
-        } else {
-            let name: String = s.iter().collect();
-            (None, name)
+        let (resource, name) = self.split_raw();
+        let decode = |raw: &[char]| match self.predicate.field.kind {
+            Kind::Literal => unescape(raw).iter().collect::<String>(),
+            _ => raw.iter().collect(),
+        };
+        (resource.as_deref().map(decode), decode(&name))
+    }
+
+    // Path returns the field name split on each (.).
+    // The (.) separator is escaped when preceded by (\).
+    // Example: labels\.app.x => ["labels.app", "x"]
+    pub fn path(&self) -> Vec<String> {
+        let decode = |raw: &[char]| match self.predicate.field.kind {
+            Kind::Literal => unescape(raw).iter().collect::<String>(),
+            _ => raw.iter().collect(),
+        };
+        self.segments().iter().map(|s| decode(s)).collect()
+    }
+
+    // split_raw splits the raw field name.
+    fn split_raw(&self) -> (Option<Vec<char>>, Vec<char>) {
+        let mut segments = self.segments();
+        let first = segments.remove(0);
+        if segments.is_empty() {
+            return (None, first);
         }
+        (Some(first), segments.join(&'.'))
     }
 
-    // // operator returns SQL operator.
-    // pub fn operator(&self) -> String {
-    //     if self.predicate.value.0.len() == 1 {
-    //         let s: String = self.predicate.operator.value.iter().collect();
-    //         if s == COLON.to_string() {
-    //             "=".to_string()
-    //         } else if s == LIKE.to_string() {
-    //             "LIKE".to_string()
-    //         } else {
-    //             s
-    //         }
-    //     } else {
-    //         "IN".to_string()
-    //     }
-    // }
+    // segments of the raw field name.
+    // Quoted names are split on the (decoded) value.
+    fn segments(&self) -> Vec<Vec<char>> {
+        let token = &self.predicate.field;
+        let s = match token.kind {
+            Kind::Literal => token.raw(),
+            _ => &token.value[..],
+        };
+        let mut segments: Vec<Vec<char>> = vec![vec![]];
+        let mut escaped = false;
+        for ch in s.iter() {
+            match ch {
+                _ if escaped => escaped = false,
+                &ESCAPE if token.kind == Kind::Literal => escaped = true,
+                '.' => {
+                    segments.push(vec![]);
+                    continue;
+                }
+                _ => {}
+            }
+            segments.last_mut().unwrap().push(*ch);
+        }
+        segments
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::get_first, clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
-    use crate::lexer::{Kind, Token, TokenValue, OR};
+    use crate::lexer::{Kind, OR, Token, TokenValue};
     use crate::parser::Parser;
 
     #[test]
@@ -201,16 +218,21 @@ mod tests {
         assert!(field.is_some());
         let field = field.unwrap();
         assert_eq!(field.name(), "category");
+        assert!(!field.negated());
         assert_eq!(
-            field.predicate.value.by_kind(vec![Kind::Operator]),
+            field.predicate.value.by_kind(&[Kind::Operator]),
             vec![
-                Token {
+                &Token {
                     kind: Kind::Operator,
-                    value: vec![OR]
+                    value: vec![OR],
+                    raw: None,
+                    span: Span::new(29, 30)
                 },
-                Token {
+                &Token {
                     kind: Kind::Operator,
-                    value: vec![OR]
+                    value: vec![OR],
+                    raw: None,
+                    span: Span::new(31, 32)
                 }
             ]
         );
@@ -218,19 +240,25 @@ mod tests {
             field
                 .predicate
                 .value
-                .by_kind(vec![Kind::Literal, Kind::String]),
+                .by_kind(&[Kind::Literal, Kind::String]),
             vec![
-                Token {
+                &Token {
                     kind: Kind::Literal,
-                    value: vec!['a']
+                    value: vec!['a'],
+                    raw: None,
+                    span: Span::new(28, 29)
                 },
-                Token {
+                &Token {
                     kind: Kind::Literal,
-                    value: vec!['b']
+                    value: vec!['b'],
+                    raw: None,
+                    span: Span::new(30, 31)
                 },
-                Token {
+                &Token {
                     kind: Kind::Literal,
-                    value: vec!['c']
+                    value: vec!['c'],
+                    raw: None,
+                    span: Span::new(32, 33)
                 }
             ]
         );
@@ -266,6 +294,23 @@ mod tests {
         assert_eq!(field.name(), "first");
     }
 
+    #[test]
+    fn test_filter_span() {
+        let filter = "name:elmer, category=(a|b|c), app.tag='x y'";
+        let p = Parser::filter(filter).unwrap();
+
+        let field = p.field("category").unwrap();
+        assert_eq!(field.span(), Span::new(12, 28));
+        assert_eq!(field.span().text(filter), "category=(a|b|c)");
+        assert_eq!(field.operator(), CompareOp::Eq);
+
+        let app = p.resource("app");
+        let field = app.field("tag").unwrap();
+        assert_eq!(field.span().text(filter), "app.tag='x y'");
+        assert_eq!(field.predicate.field.span.text(filter), "app.tag");
+        assert_eq!(field.predicate.value.0[0].span.text(filter), "'x y'");
+    }
+
     #[test]
     fn test_filter_resource_numeric() {
         let p = Parser::filter("app.name=test,app.tag.id=0");
@@ -286,6 +331,71 @@ mod tests {
         assert!(option.is_some());
         let field = option.unwrap();
         let value = field.predicate.value.0.get(0).map(|c| c.as_value());
-        assert_eq!(value, Some(TokenValue::Number(0)));
+        assert_eq!(value, Some(TokenValue::Int(0)));
+    }
+
+    #[test]
+    fn test_filter_display() {
+        let cases = [
+            ("", ""),
+            ("name:elmer", "name:elmer"),
+            ("name = elmer , age>=18", "name=elmer,age>=18"),
+            (
+                "name=(jim|crossley),tags!=(a,b)",
+                "name=(jim|crossley),tags!=(a,b)",
+            ),
+            ("name=(jim)", "name=jim"),
+            ("age='18'", "age='18'"),
+            ("filterText~'like =<>~'", "filterText~'like =<>~'"),
+            ("address=\"O'Brien st\"", "address=\"O'Brien st\""),
+            ("'first name'=elmer", "'first name'=elmer"),
+            ("labels=('part-of: x','a|b')", "labels=('part-of: x','a|b')"),
+            (
+                "updated>=2024-05-01T10:00:00+05:30",
+                "updated>=2024-05-01T10:00:00+05:30",
+            ),
+            (r"path='C:\\Users\\'", r"path='C:\\Users\\'"),
+            (r#"q="it's \"quoted\"""#, r#"q='it\'s "quoted"'"#),
+            ("name=''", "name=''"),
+        ];
+        for (filter, expected) in cases {
+            let p = Parser::filter(filter).unwrap();
+            let query = p.to_string();
+            assert_eq!(query, expected);
+            // round-trip
+            let reparsed = Parser::filter(&query).unwrap();
+            assert_eq!(reparsed.to_string(), query);
+            let tokens = |f: &Filter| -> Vec<(Kind, String)> {
+                f.predicates
+                    .iter()
+                    .flat_map(|p| [std::slice::from_ref(&p.field), p.value.0.as_slice()].concat())
+                    .map(|t| (t.kind, t.to_string()))
+                    .collect()
+            };
+            assert_eq!(tokens(&reparsed), tokens(&p));
+        }
+    }
+
+    #[test]
+    fn test_filter_escapes() {
+        let p = Parser::filter(r"app\.name=x,app.tag\.id=a\,b,'a.b'=1").unwrap();
+        let split: Vec<(Option<String>, String)> = p
+            .predicates
+            .iter()
+            .map(|p| Field { predicate: p }.split())
+            .collect();
+        assert_eq!(
+            split,
+            vec![
+                (None, "app.name".to_string()),
+                (Some("app".to_string()), "tag.id".to_string()),
+                (Some("a".to_string()), "b".to_string()),
+            ]
+        );
+        let app = p.resource("app");
+        let field = app.field("tag.id").unwrap();
+        assert_eq!(field.value().0[0].to_string(), "a,b");
+        assert_eq!(field.predicate.field.query(), r"tag\.id");
+        assert_eq!(p.to_string(), r"app\.name=x,app.tag\.id=a\,b,'a.b'=1");
     }
 }
@@ -0,0 +1,157 @@
+use crate::decimal::Decimal;
+use crate::lexer::TokenValue;
+use crate::record::{Record, lookup};
+use serde_json::Value;
+
+// Record for JSON documents.
+// Dotted names walk nested objects. Example: labels.app=web matches
+//   {"labels": {"app": "web"}}
+// An escaped (.) is part of the key. Example: labels\.app=web matches
+//   {"labels.app": "web"}
+// Arrays match when any element matches. Keys are matched exactly,
+// then case-insensitive; see record::lookup(). Null, objects and missing
+// keys have no value.
+impl Record for Value {
+    fn get(&self, resource: Option<&str>, name: &str) -> Option<TokenValue> {
+        self.values(resource, name).into_iter().next()
+    }
+
+    fn values(&self, resource: Option<&str>, name: &str) -> Vec<TokenValue> {
+        let mut path: Vec<&str> = vec![];
+        if let Some(resource) = resource {
+            path.push(resource);
+        }
+        path.extend(name.split('.'));
+        let mut values: Vec<TokenValue> = vec![];
+        walk(self, &path, &mut values);
+        values
+    }
+
+    fn path(&self, path: &[String]) -> Vec<TokenValue> {
+        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+        let mut values: Vec<TokenValue> = vec![];
+        walk(self, &path, &mut values);
+        values
+    }
+}
+
+// walk the path collecting values.
+fn walk(value: &Value, path: &[&str], values: &mut Vec<TokenValue>) {
+    match (value, path.split_first()) {
+        (Value::Array(items), _) => {
+            for item in items.iter() {
+                walk(item, path, values);
+            }
+        }
+        (Value::Object(object), Some((key, path))) => {
+            if let Some(found) = lookup(object.get(*key), object.iter(), key) {
+                walk(found, path, values);
+            }
+        }
+        (_, Some(_)) => {}
+        (value, None) => values.extend(scalar(value)),
+    }
+}
+
+// scalar value.
+// Numbers are Int when possible, Decimal when integral, else Float.
+fn scalar(value: &Value) -> Option<TokenValue> {
+    match value {
+        Value::String(s) => Some(TokenValue::String(s.clone())),
+        Value::Bool(b) => Some(TokenValue::Bool(*b)),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Some(TokenValue::Int(i)),
+            None if n.is_u64() => Decimal::parse(&n.to_string()).map(TokenValue::Decimal),
+            None => n.as_f64().map(TokenValue::Float),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use serde_json::json;
+
+    fn document() -> Value {
+        json!({
+            "name": "web-1",
+            "replicas": 3,
+            "cpu": 0.5,
+            "size": 18446744073709551615u64,
+            "ready": true,
+            "owner": null,
+            "labels": {"app": "web", "tier": "frontend"},
+            "annotations": {"app.kubernetes.io/name": "web", "app": {"kubernetes": "x"}},
+            "tags": ["a", "b"],
+            "containers": [
+                {"name": "nginx", "ports": [80, 443]},
+                {"name": "sidecar", "ports": [9090]}
+            ],
+            "createdAt": "2024-05-01T10:00:00Z"
+        })
+    }
+
+    fn matches(filter: &str) -> bool {
+        Parser::filter(filter).unwrap().matches(&document())
+    }
+
+    #[test]
+    fn test_json_fields() {
+        assert!(matches("name=web-1,replicas>=3,cpu<1,ready=true"));
+        assert!(matches("size>18446744073709551614"));
+        assert!(matches("labels.app=web,labels.tier~front*"));
+        assert!(!matches("labels.app=db"));
+        assert!(matches("createdAt>2024-05-01T09:00:00Z"));
+        assert!(!matches("owner=null"));
+        assert!(matches("owner!=null"));
+        assert!(!matches("labels=web"));
+        assert!(!matches("name.first=web-1"));
+    }
+
+    #[test]
+    fn test_json_escaped_keys() {
+        assert!(matches("annotations.app\\.kubernetes\\.io/name=web"));
+        assert!(matches("annotations.app.kubernetes=x"));
+        assert!(!matches("annotations.app\\.kubernetes=x"));
+        assert!(!matches("labels\\.app=web"));
+
+        let document = json!({"labels.app": "web", "labels": {"app": "db"}});
+        let filter = Parser::filter("labels\\.app=web,labels.app=db").unwrap();
+        assert!(filter.matches(&document));
+        let filter = Parser::filter("'labels.app'=web").unwrap();
+        assert!(!filter.matches(&document));
+    }
+
+    #[test]
+    fn test_json_key_case() {
+        let document = json!({"Name": "a", "name": "b", "Ünit": 1, "Labels": {"App": "web"}});
+        let matches = |filter: &str| Parser::filter(filter).unwrap().matches(&document);
+        assert!(matches("Name=a"));
+        assert!(matches("name=b"));
+        assert!(!matches("Name=b"));
+        assert!(matches("Ünit=1"));
+        assert!(matches("ünit=1"));
+        assert!(matches("labels.app=web,LABELS.APP=web"));
+        assert!(!matches("units=1"));
+    }
+
+    #[test]
+    fn test_json_arrays() {
+        assert!(matches("tags=a"));
+        assert!(matches("tags=(a,b)"));
+        assert!(!matches("tags=(a,c)"));
+        assert!(matches("containers.name=sidecar"));
+        assert!(matches("containers.ports=443"));
+        assert!(matches("containers.ports>9000"));
+        assert!(!matches("containers.ports=22"));
+        assert!(matches("containers.ports!=22"));
+    }
+
+    #[test]
+    fn test_json_expr() {
+        let expr = Parser::expr("labels.app=db|(tags:b,!ready=false)").unwrap();
+        assert!(expr.matches(&document()));
+    }
+}
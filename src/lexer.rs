@@ -1,4 +1,9 @@
+use crate::datetime::{self, Date, DateTime, Duration};
+use crate::decimal::Decimal;
+use crate::error::{FilterError, Span};
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
 
 pub const COLON: char = ':';
 pub const COMMA: char = ',';
@@ -16,93 +21,327 @@ pub const SPACE: char = ' ';
 pub const LPAREN: char = '(';
 pub const RPAREN: char = ')';
 
-// Lexer token reader.
-#[derive(Debug, PartialEq)]
-pub struct Lexer {
-    pub tokens: Vec<Token>,
-    pub index: usize,
+// Characters ending a literal; (\) makes them part of it.
+// See: sequence().
+const SPECIAL: [char; 14] = [
+    COLON, COMMA, OR, EQ, LIKE, NOT, LT, GT, QUOTE, SQUOTE, ESCAPE, SPACE, LPAREN, RPAREN,
+];
+
+// Lexer streaming token reader.
+// Borrows the filter and scans one token per next(); use peek() to look
+// ahead without consuming. Scanning stops after the first error.
+// A (:) ends a field; in values it may be part of a timestamp.
+// Example: 2024-05-01T10:x => 2024-05-01T10 : x
+// Example:
+//   let mut lexer = Lexer::new("name=jim");
+//   while let Some(token) = lexer.next() {
+//       let token = token?;
+//   }
+#[derive(Debug, Clone)]
+pub struct Lexer<'a> {
+    filter: &'a str,
+    // offset of the next character.
+    offset: usize,
+    // end of the last token scanned.
+    end: usize,
+    peeked: Option<Option<Result<Lexeme<'a>, FilterError>>>,
+    done: bool,
+    // scanning a field: at the start, after a separator, `(` of a
+    // group or `!`.
+    field: bool,
+    // scanning the items of a list.
+    list: bool,
 }
 
-impl Lexer {
-    // With builds with the specified filter.
-    pub fn with(filter: String) -> Result<Self, String> {
-        let mut tokens: Vec<Token> = vec![];
+impl<'a> Lexer<'a> {
+    pub fn new(filter: &'a str) -> Self {
+        Self {
+            filter,
+            offset: 0,
+            end: 0,
+            peeked: None,
+            done: false,
+            field: true,
+            list: false,
+        }
+    }
 
-        let mut reader = Reader::from(&filter);
-        let mut bfr: Vec<char> = vec![];
+    // Tokens scans all tokens of the filter.
+    pub fn tokens(filter: &str) -> Result<Vec<Token>, FilterError> {
+        Lexer::new(filter)
+            .map(|token| token.map(|t| Token::from(&t)))
+            .collect()
+    }
 
-        let push = |bfr: &mut Vec<char>, tokens: &mut Vec<Token>, kind: Kind| {
-            if !bfr.is_empty() {
-                tokens.push(Token {
-                    kind,
-                    value: bfr.clone(),
-                });
-                bfr.clear();
-            }
+    // Peek returns the next token without consuming it.
+    pub fn peek(&mut self) -> Option<&Result<Lexeme<'a>, FilterError>> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.scan());
+        }
+        self.peeked.as_ref().and_then(|t| t.as_ref())
+    }
+
+    // End returns the byte offset after the last token scanned.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    // scan the next token.
+    fn scan(&mut self) -> Option<Result<Lexeme<'a>, FilterError>> {
+        if self.done {
+            return None;
+        }
+        let rest = self.filter[self.offset..].trim_start_matches(SPACE);
+        self.offset = self.filter.len() - rest.len();
+        let ch = rest.chars().next()?;
+        let scanned = match ch {
+            QUOTE | SQUOTE => self.quoted(ch),
+            LPAREN => Ok(self.single(Kind::Lparen, ch)),
+            RPAREN => Ok(self.single(Kind::Rparen, ch)),
+            COMMA | OR => Ok(self.single(Kind::Operator, ch)),
+            COLON | EQ | LIKE | NOT | LT | GT => Ok(self.operator()),
+            _ => self.literal(),
         };
+        match &scanned {
+            Ok(token) => {
+                self.end = token.span.end;
+                self.follow(token);
+            }
+            Err(_) => self.done = true,
+        }
+        Some(scanned)
+    }
 
-        while let Some(ch) = reader.next() {
-            match ch {
-                QUOTE | SQUOTE => {
-                    reader.put();
-                    push(&mut bfr, &mut tokens, Kind::Literal);
-                    let mut quoted = Quoted {
-                        reader: &mut reader,
-                    };
-                    let quoted = quoted.read()?;
-
-                    tokens.push(quoted);
-                }
-                SPACE => push(&mut bfr, &mut tokens, Kind::Literal),
-                LPAREN => {
-                    bfr.push(ch);
-                    push(&mut bfr, &mut tokens, Kind::Lparen);
-                }
-                RPAREN => {
-                    push(&mut bfr, &mut tokens, Kind::Literal);
-                    bfr.push(ch);
-                    push(&mut bfr, &mut tokens, Kind::Rparen);
-                }
-                COLON | COMMA | OR | EQ | LIKE | NOT | LT | GT => {
-                    reader.put();
-                    push(&mut bfr, &mut tokens, Kind::Literal);
+    // follow the token: set whether a field or a value is next.
+    fn follow(&mut self, token: &Lexeme) {
+        match token.kind {
+            Kind::Lparen if !self.field => self.list = true,
+            Kind::Lparen => {}
+            Kind::Rparen => {
+                self.list = false;
+                self.field = false;
+            }
+            Kind::Operator if token.is(AND) || token.is(OR) => self.field = !self.list,
+            Kind::Operator => self.field = token.is(NOT),
+            Kind::Literal | Kind::String => self.field = false,
+        }
+    }
 
-                    let mut operator = Operator {
-                        reader: &mut reader,
-                    };
-                    let operator = operator.read()?;
+    // single character token.
+    fn single(&mut self, kind: Kind, ch: char) -> Lexeme<'a> {
+        let start = self.offset;
+        self.offset += ch.len_utf8();
+        let raw = &self.filter[start..self.offset];
+        Lexeme {
+            kind,
+            value: Cow::Borrowed(raw),
+            raw,
+            span: Span::new(start, self.offset),
+        }
+    }
+
+    // operator token.
+    // Separators (`,` `|`) are not part of an operator.
+    fn operator(&mut self) -> Lexeme<'a> {
+        let start = self.offset;
+        let rest = &self.filter[start..];
+        self.offset += rest.len()
+            - rest
+                .trim_start_matches([COLON, EQ, LIKE, NOT, LT, GT])
+                .len();
+        let raw = &self.filter[start..self.offset];
+        Lexeme {
+            kind: Kind::Operator,
+            value: Cow::Borrowed(raw),
+            raw,
+            span: Span::new(start, self.offset),
+        }
+    }
 
-                    tokens.push(operator);
+    // literal token.
+    // Ends at a space, quote, parenthesis, separator or operator.
+    // Escape sequences as in quoted strings. Example: a\,b
+    fn literal(&mut self) -> Result<Lexeme<'a>, FilterError> {
+        let filter = self.filter;
+        let start = self.offset;
+        let mut end = filter.len();
+        // value once an escape is found.
+        let mut decoded: Option<String> = None;
+        let mut chars = filter[start..]
+            .char_indices()
+            .map(|(i, ch)| (start + i, ch))
+            .peekable();
+        while let Some((at, ch)) = chars.next() {
+            let stop = match ch {
+                ESCAPE => {
+                    let decoded = decoded.get_or_insert_with(|| filter[start..at].to_string());
+                    if !sequence(&mut chars, at, decoded)? {
+                        return Err(FilterError::InvalidEscape {
+                            sequence: ESCAPE.to_string(),
+                            span: Span::new(at, filter.len()),
+                        });
+                    }
+                    continue;
+                }
+                QUOTE | SQUOTE | SPACE | LPAREN | RPAREN | COMMA | OR => true,
+                // time of a timestamp value. Example: 2024-05-01T10:00:00Z
+                COLON => {
+                    self.field
+                        || !datetime::timestamp(decoded.as_deref().unwrap_or(&filter[start..at]))
                 }
-                _ => bfr.push(ch),
+                EQ | LIKE | NOT | LT | GT => true,
+                _ => false,
+            };
+            if stop {
+                end = at;
+                break;
+            }
+            if let Some(decoded) = decoded.as_mut() {
+                decoded.push(ch);
             }
         }
+        self.offset = end;
+        let raw = &filter[start..end];
+        Ok(Lexeme {
+            kind: Kind::Literal,
+            value: decoded.map_or(Cow::Borrowed(raw), Cow::Owned),
+            raw,
+            span: Span::new(start, end),
+        })
+    }
 
-        push(&mut bfr, &mut tokens, Kind::Literal);
-        Ok(Self { tokens, index: 0 })
+    // quoted string token.
+    // Escape sequences as in literals; see sequence().
+    // Other characters, including newlines, are taken as is.
+    fn quoted(&mut self, quote: char) -> Result<Lexeme<'a>, FilterError> {
+        let filter = self.filter;
+        let start = self.offset;
+        let first = start + quote.len_utf8();
+        // value once an escape is found.
+        let mut decoded: Option<String> = None;
+        let mut chars = filter[first..]
+            .char_indices()
+            .map(|(i, ch)| (first + i, ch))
+            .peekable();
+        while let Some((at, ch)) = chars.next() {
+            if ch == quote {
+                let raw = &filter[first..at];
+                self.offset = at + ch.len_utf8();
+                return Ok(Lexeme {
+                    kind: Kind::String,
+                    value: decoded.map_or(Cow::Borrowed(raw), Cow::Owned),
+                    raw,
+                    span: Span::new(start, self.offset),
+                });
+            }
+            if ch == ESCAPE {
+                let decoded = decoded.get_or_insert_with(|| {
+                    // the value is no longer than the raw value.
+                    let len = at - first + closing(&filter[at..], quote);
+                    let mut value = String::with_capacity(len);
+                    value.push_str(&filter[first..at]);
+                    value
+                });
+                if !sequence(&mut chars, at, decoded)? {
+                    break;
+                }
+            } else if let Some(decoded) = decoded.as_mut() {
+                decoded.push(ch);
+            }
+        }
+        Err(FilterError::UnterminatedQuote {
+            quote,
+            span: Span::new(start, filter.len()),
+        })
     }
+}
 
-    // next returns the next token.
-    pub fn next(&mut self) -> Option<Token> {
-        if self.index < self.tokens.len() {
-            let token = self.tokens.get(self.index);
-            self.index += 1;
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Lexeme<'a>, FilterError>;
 
-            token.cloned()
-        } else {
-            None
+    // next returns the next token.
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(token) => token,
+            None => self.scan(),
         }
     }
+}
 
-    // Put rewinds the lexer by 1 token.
-    pub fn put(&mut self) {
-        if self.index > 0 {
-            self.index -= 1;
+// closing returns the offset of the unescaped quote in the text,
+// else the length of the text.
+fn closing(text: &str, quote: char) -> usize {
+    let mut bytes = text.bytes().enumerate();
+    while let Some((i, b)) = bytes.next() {
+        match b as char {
+            ESCAPE => {
+                bytes.next();
+            }
+            ch if ch == quote => return i,
+            _ => {}
         }
     }
+    text.len()
 }
 
-#[derive(Debug, PartialEq, Clone)]
+// sequence decodes the escape sequence into the value; (\) is at `at`.
+// The same sequences apply to literals and quoted strings:
+//   \n  \t  \r        newline, tab, carriage return.
+//   \u{7FFF}         unicode scalar value (1-6 hex digits).
+//   \c               the character when special (quote, (\), space,
+//                    parenthesis, separator, operator) or (.).
+// Other sequences are kept as written. Example: 'C:\Users' => C:\Users
+// Returns false when (\) ends the input.
+fn sequence(
+    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+    at: usize,
+    value: &mut String,
+) -> Result<bool, FilterError> {
+    let Some((_, ch)) = chars.next() else {
+        return Ok(false);
+    };
+    let decoded = match ch {
+        _ if SPECIAL.contains(&ch) || ch == '.' => ch,
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        'u' if matches!(chars.peek(), Some((_, '{'))) => {
+            chars.next();
+            // sequence as written so far.
+            let invalid = |hex: &str, closed: bool, end: usize| FilterError::InvalidEscape {
+                sequence: format!("\\u{{{}{}", hex, if closed { "}" } else { "" }),
+                span: Span::new(at, end),
+            };
+            let mut hex = String::new();
+            // end of the sequence as written so far; after `\u{`.
+            let mut last = at + 3;
+            let end = loop {
+                match chars.next() {
+                    Some((i, '}')) => break i + 1,
+                    Some((i, ch)) if ch.is_ascii_hexdigit() && hex.len() < 6 => {
+                        hex.push(ch);
+                        last = i + 1;
+                    }
+                    Some((i, _)) => return Err(invalid(&hex, false, i)),
+                    None => return Err(invalid(&hex, false, last)),
+                }
+            };
+            u32::from_str_radix(&hex, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| invalid(&hex, true, end))?
+        }
+        _ => {
+            value.push(ESCAPE);
+            ch
+        }
+    };
+    value.push(decoded);
+    Ok(true)
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Kind {
     Literal,
     String,
@@ -112,10 +351,36 @@ pub enum Kind {
 }
 
 // Token scanned token.
-#[derive(Debug, PartialEq, Clone)]
+// The value is decoded; the raw value is as written, with escapes and
+// without the quotes. Example: a\,b => value: a,b raw: a\,b
+// The span is the byte range of the token in the filter.
+// The raw value is None when it is the value; see raw(). Tokens compare
+// by raw(), so Some(value) equals None.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub kind: Kind,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::chars"))]
     pub value: Vec<char>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "crate::serialize::chars_option"
+        )
+    )]
+    pub raw: Option<Vec<char>>,
+    pub span: Span,
+}
+
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.value == other.value
+            && self.raw() == other.raw()
+            && self.span == other.span
+    }
 }
 
 impl Display for Token {
@@ -125,136 +390,224 @@ impl Display for Token {
     }
 }
 
-#[derive(Debug, PartialEq)]
+// Lexeme token borrowing the filter.
+// The value is decoded and borrowed unless the token has escapes; the raw
+// value is as written, without the quotes. Token::from() makes the owned
+// token kept by the parser.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lexeme<'a> {
+    pub kind: Kind,
+    pub value: Cow<'a, str>,
+    pub raw: &'a str,
+    pub span: Span,
+}
+
+impl Lexeme<'_> {
+    // Is returns true when the value is the character.
+    pub fn is(&self, ch: char) -> bool {
+        let mut chars = self.value.chars();
+        chars.next() == Some(ch) && chars.next().is_none()
+    }
+}
+
+impl Display for Lexeme<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl From<&Lexeme<'_>> for Token {
+    fn from(lexeme: &Lexeme<'_>) -> Self {
+        Token {
+            kind: lexeme.kind,
+            value: lexeme.value.chars().collect(),
+            raw: (lexeme.value != lexeme.raw).then(|| lexeme.raw.chars().collect()),
+            span: lexeme.span,
+        }
+    }
+}
+
+// TokenValue typed value.
+// Decimal is used for numbers not representable as Int or Float.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenValue {
     String(String),
-    Number(usize),
+    Int(i64),
+    Float(f64),
+    Decimal(Decimal),
     Bool(bool),
+    Date(Date),
+    DateTime(DateTime),
+    Duration(Duration),
 }
 
 impl Display for TokenValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             TokenValue::String(val) => write!(f, "{}", val),
-            TokenValue::Number(val) => write!(f, "{}", val),
+            TokenValue::Int(val) => write!(f, "{}", val),
+            TokenValue::Float(val) => write!(f, "{}", val),
+            TokenValue::Decimal(val) => write!(f, "{}", val),
             TokenValue::Bool(val) => write!(f, "{}", val),
+            TokenValue::Date(val) => write!(f, "{}", val),
+            TokenValue::DateTime(val) => write!(f, "{}", val),
+            TokenValue::Duration(val) => write!(f, "{}", val),
         }
     }
 }
 
 impl Token {
-    pub fn as_value(&self) -> TokenValue {
-        let v: String = self.value.iter().collect();
+    // Raw returns the raw value; the value when not set.
+    pub fn raw(&self) -> &[char] {
+        self.raw.as_deref().unwrap_or(&self.value)
+    }
+
+    // unless_value returns the raw value, None when it is the value.
+    pub(crate) fn unless_value(raw: Vec<char>, value: &[char]) -> Option<Vec<char>> {
+        (raw != value).then_some(raw)
+    }
+
+    // Query returns the token as written in a value.
+    // Literals are written raw, else escaped; literals that cannot be
+    // written (empty) and strings are quoted.
+    pub fn query(&self) -> String {
+        self.written(false)
+    }
+
+    // FieldQuery returns the token as written in a field.
+    // A (:) in a literal field is escaped. Example: 2024-05-01T10\:00
+    pub fn field_query(&self) -> String {
+        self.written(true)
+    }
+
+    // written returns the token as written in a field or a value.
+    fn written(&self, field: bool) -> String {
         match self.kind {
             Kind::Literal => {
-                if let Ok(n) = v.parse::<usize>() {
-                    TokenValue::Number(n)
-                } else if let Ok(b) = v.parse::<bool>() {
-                    TokenValue::Bool(b)
+                let raw: String = self.raw().iter().collect();
+                if Self::scans(&raw, &self.value, field) {
+                    return raw;
+                }
+                let escaped = escape(&self.value);
+                if Self::scans(&escaped, &self.value, field) {
+                    escaped
                 } else {
-                    TokenValue::String(v)
+                    quote(&self.value)
                 }
             }
-            _ => TokenValue::String(v),
+            Kind::String => quote(&self.value),
+            _ => self.to_string(),
         }
     }
-}
-
-// Reader scan the input.
-pub struct Reader {
-    pub chars: Vec<char>,
-    pub index: usize,
-}
-
-impl Reader {
-    pub fn from(input: &str) -> Self {
-        let chars: Vec<char> = input.chars().collect();
-        Self { chars, index: 0 }
-    }
-
-    // Next character.
-    pub fn next(&mut self) -> Option<char> {
-        if self.index < self.chars.len() {
-            let current_index = self.index;
-            self.index += 1;
 
-            self.chars.get(current_index).cloned()
-        } else {
-            None
-        }
+    // bare returns true when the value scans as a single literal
+    // without escapes, in a field or a value.
+    pub(crate) fn bare(v: &str, field: bool) -> bool {
+        let value: Vec<char> = v.chars().collect();
+        !value.contains(&ESCAPE) && Self::scans(v, &value, field)
     }
 
-    // Put rewinds one character.
-    pub fn put(&mut self) {
-        if self.index > 0 {
-            self.index -= 1;
+    // scans returns true when the text scans as a single literal with the
+    // value, in a field or a value.
+    fn scans(text: &str, value: &[char], field: bool) -> bool {
+        let mut lexer = Lexer::new(text);
+        lexer.field = field;
+        match (lexer.next(), lexer.next()) {
+            (Some(Ok(token)), None) => {
+                token.kind == Kind::Literal && token.value.chars().eq(value.iter().copied())
+            }
+            _ => false,
         }
     }
-}
 
-// Quoted string token reader.
-pub struct Quoted<'a> {
-    reader: &'a mut Reader,
-}
-
-impl Quoted<'_> {
-    // Read token
-    pub fn read(&mut self) -> Result<Token, String> {
-        let mut last_ch: Option<char> = None;
-        let mut bfr: Vec<char> = vec![];
-        let quote = self
-            .reader
-            .next()
-            .ok_or("Could not get first quoted character".to_string())?;
-
-        loop {
-            match self.reader.next() {
-                Some(ch) => {
-                    if ch == quote {
-                        if let Some(last_ch) = last_ch {
-                            if last_ch != ESCAPE {
-                                break Ok(Token {
-                                    kind: Kind::String,
-                                    value: bfr.clone(),
-                                });
-                            }
-                        }
+    // AsValue returns the value guessed from the literal.
+    // Numbers with a (.) are Float; integers beyond i64 are Decimal.
+    // Quoted strings are always String.
+    // Use FieldType::coerce() for values of a declared type.
+    pub fn as_value(&self) -> TokenValue {
+        let v: String = self.value.iter().collect();
+        match self.kind {
+            Kind::Literal => {
+                if let Ok(n) = v.parse::<i64>() {
+                    TokenValue::Int(n)
+                } else if let Some(d) = Decimal::parse(&v) {
+                    if v.contains('.') {
+                        TokenValue::Float(d.to_f64())
                     } else {
-                        bfr.push(ch);
+                        TokenValue::Decimal(d)
                     }
-                    last_ch = Some(ch);
+                } else if let Ok(b) = v.parse::<bool>() {
+                    TokenValue::Bool(b)
+                } else if let Some(d) = Date::parse(&v) {
+                    TokenValue::Date(d)
+                } else if let Some(dt) = DateTime::parse(&v) {
+                    TokenValue::DateTime(dt)
+                } else if let Some(d) = Duration::parse(&v) {
+                    TokenValue::Duration(d)
+                } else {
+                    TokenValue::String(v)
                 }
-                None => break Err(format!("End {} not found.", quote)),
             }
+            _ => TokenValue::String(v),
         }
     }
 }
 
-// Operator token reader.
-pub struct Operator<'a> {
-    reader: &'a mut Reader,
+// Escape returns the value with special characters escaped by (\).
+// Example: a,b => a\,b
+pub fn escape(value: &[char]) -> String {
+    let mut escaped = String::new();
+    for ch in value.iter() {
+        if SPECIAL.contains(ch) {
+            escaped.push(ESCAPE);
+        }
+        escaped.push(*ch);
+    }
+    escaped
 }
 
-impl Operator<'_> {
-    // Read token
-    pub fn read(&mut self) -> Result<Token, String> {
-        let mut bfr: Vec<char> = vec![];
-        loop {
-            match self.reader.next() {
-                Some(ch) => match ch {
-                    COLON | COMMA | OR | EQ | LIKE | NOT | LT | GT => bfr.push(ch),
-                    _ => {
-                        self.reader.put();
-                        break Ok(Token {
-                            kind: Kind::Operator,
-                            value: bfr,
-                        });
-                    }
-                },
-                None => break Err("End of operator not found".to_string()),
-            }
+// Unescape returns the value of the escaped text. See: sequence().
+// Invalid sequences are kept as written.
+pub fn unescape(raw: &[char]) -> Vec<char> {
+    let text: String = raw.iter().collect();
+    let mut value = String::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((at, ch)) = chars.next() {
+        if ch != ESCAPE {
+            value.push(ch);
+            continue;
+        }
+        let mut decoded = String::new();
+        if let Ok(true) = sequence(&mut chars, at, &mut decoded) {
+            value.push_str(&decoded);
+        } else {
+            value.push_str(&text[at..]);
+            break;
+        }
+    }
+    value.chars().collect()
+}
+
+// Quote returns the value quoted.
+// Uses (") when the value contains (') but not ("), else (').
+// The quote and (\) are escaped.
+pub fn quote(value: &[char]) -> String {
+    let quote = if value.contains(&SQUOTE) && !value.contains(&QUOTE) {
+        QUOTE
+    } else {
+        SQUOTE
+    };
+    let mut quoted = String::new();
+    quoted.push(quote);
+    for ch in value.iter() {
+        if *ch == quote || *ch == ESCAPE {
+            quoted.push(ESCAPE);
         }
+        quoted.push(*ch);
     }
+    quoted.push(quote);
+    quoted
 }
 
 #[cfg(test)]
@@ -263,335 +616,777 @@ mod tests {
 
     #[test]
     fn test_lexer1() {
-        let result = Lexer::with("name:elmer,age:20".to_string());
+        let result = Lexer::tokens("name:elmer,age:20");
         assert_eq!(
             result,
-            Ok(Lexer {
-                tokens: vec![
-                    Token {
-                        kind: Kind::Literal,
-                        value: "name".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![COLON]
-                    },
-                    Token {
-                        kind: Kind::Literal,
-                        value: "elmer".chars().collect()
-                    },
-                    //
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![COMMA]
-                    },
-                    //
-                    Token {
-                        kind: Kind::Literal,
-                        value: "age".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![COLON]
-                    },
-                    Token {
-                        kind: Kind::Literal,
-                        value: "20".chars().collect()
-                    },
-                ],
-                index: 0
-            })
+            Ok(vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "name".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 4)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COLON],
+                    raw: None,
+                    span: Span::new(4, 5)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "elmer".chars().collect(),
+                    raw: None,
+                    span: Span::new(5, 10)
+                },
+                //
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COMMA],
+                    raw: None,
+                    span: Span::new(10, 11)
+                },
+                //
+                Token {
+                    kind: Kind::Literal,
+                    value: "age".chars().collect(),
+                    raw: None,
+                    span: Span::new(11, 14)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COLON],
+                    raw: None,
+                    span: Span::new(14, 15)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "20".chars().collect(),
+                    raw: None,
+                    span: Span::new(15, 17)
+                },
+            ])
         );
     }
 
     #[test]
     fn test_or() {
-        let result = Lexer::with("name:\"one|two\"".to_string());
+        let result = Lexer::tokens("name:\"one|two\"");
         assert_eq!(
             result,
-            Ok(Lexer {
-                tokens: vec![
-                    Token {
-                        kind: Kind::Literal,
-                        value: "name".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![COLON]
-                    },
-                    Token {
-                        kind: Kind::String,
-                        value: "one|two".chars().collect()
-                    },
-                ],
-                index: 0
-            })
+            Ok(vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "name".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 4)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COLON],
+                    raw: None,
+                    span: Span::new(4, 5)
+                },
+                Token {
+                    kind: Kind::String,
+                    value: "one|two".chars().collect(),
+                    raw: None,
+                    span: Span::new(5, 14)
+                },
+            ])
         );
     }
 
     #[test]
     fn test_equal() {
-        let result = Lexer::with("name:\"one=two\"".to_string());
+        let result = Lexer::tokens("name:\"one=two\"");
         assert_eq!(
             result,
-            Ok(Lexer {
-                tokens: vec![
-                    Token {
-                        kind: Kind::Literal,
-                        value: "name".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![COLON]
-                    },
-                    Token {
-                        kind: Kind::String,
-                        value: "one=two".chars().collect()
-                    },
-                ],
-                index: 0
-            })
+            Ok(vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "name".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 4)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COLON],
+                    raw: None,
+                    span: Span::new(4, 5)
+                },
+                Token {
+                    kind: Kind::String,
+                    value: "one=two".chars().collect(),
+                    raw: None,
+                    span: Span::new(5, 14)
+                },
+            ])
         );
     }
 
     #[test]
     fn test_grouping() {
-        let result = Lexer::with("name:\"(one|two)\"".to_string());
+        let result = Lexer::tokens("name:\"(one|two)\"");
         assert_eq!(
             result,
-            Ok(Lexer {
-                tokens: vec![
-                    Token {
-                        kind: Kind::Literal,
-                        value: "name".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![COLON]
-                    },
-                    Token {
-                        kind: Kind::String,
-                        value: "(one|two)".chars().collect()
-                    },
-                ],
-                index: 0
-            })
+            Ok(vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "name".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 4)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COLON],
+                    raw: None,
+                    span: Span::new(4, 5)
+                },
+                Token {
+                    kind: Kind::String,
+                    value: "(one|two)".chars().collect(),
+                    raw: None,
+                    span: Span::new(5, 16)
+                },
+            ])
         );
     }
 
     #[test]
     fn test_escaped() {
-        let result = Lexer::with("name:\"hello world\"".to_string());
+        let result = Lexer::tokens("name:\"hello world\"");
         assert_eq!(
             result,
-            Ok(Lexer {
-                tokens: vec![
-                    Token {
-                        kind: Kind::Literal,
-                        value: "name".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![COLON]
-                    },
-                    Token {
-                        kind: Kind::String,
-                        value: "hello world".chars().collect()
-                    },
-                ],
-                index: 0
-            })
+            Ok(vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "name".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 4)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COLON],
+                    raw: None,
+                    span: Span::new(4, 5)
+                },
+                Token {
+                    kind: Kind::String,
+                    value: "hello world".chars().collect(),
+                    raw: None,
+                    span: Span::new(5, 18)
+                },
+            ])
         );
     }
 
     #[test]
     fn test_escaped_and_operator() {
-        let result = Lexer::with("name = \"elmer\" , age > 20".to_string());
+        let result = Lexer::tokens("name = \"elmer\" , age > 20");
         assert_eq!(
             result,
-            Ok(Lexer {
-                tokens: vec![
-                    Token {
-                        kind: Kind::Literal,
-                        value: "name".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![EQ]
-                    },
-                    Token {
-                        kind: Kind::String,
-                        value: "elmer".chars().collect()
-                    },
-                    //
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![COMMA]
-                    },
-                    //
-                    Token {
-                        kind: Kind::Literal,
-                        value: "age".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![GT]
-                    },
-                    Token {
-                        kind: Kind::Literal,
-                        value: "20".chars().collect()
-                    },
-                ],
-                index: 0
-            })
+            Ok(vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "name".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 4)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![EQ],
+                    raw: None,
+                    span: Span::new(5, 6)
+                },
+                Token {
+                    kind: Kind::String,
+                    value: "elmer".chars().collect(),
+                    raw: None,
+                    span: Span::new(7, 14)
+                },
+                //
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COMMA],
+                    raw: None,
+                    span: Span::new(15, 16)
+                },
+                //
+                Token {
+                    kind: Kind::Literal,
+                    value: "age".chars().collect(),
+                    raw: None,
+                    span: Span::new(17, 20)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![GT],
+                    raw: None,
+                    span: Span::new(21, 22)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "20".chars().collect(),
+                    raw: None,
+                    span: Span::new(23, 25)
+                },
+            ])
         );
     }
 
     #[test]
     fn test_like_operator() {
-        let result = Lexer::with("name~elmer*".to_string());
+        let result = Lexer::tokens("name~elmer*");
         assert_eq!(
             result,
-            Ok(Lexer {
-                tokens: vec![
-                    Token {
-                        kind: Kind::Literal,
-                        value: "name".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![LIKE]
-                    },
-                    Token {
-                        kind: Kind::Literal,
-                        value: "elmer*".chars().collect()
-                    },
-                ],
-                index: 0
-            })
+            Ok(vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "name".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 4)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![LIKE],
+                    raw: None,
+                    span: Span::new(4, 5)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "elmer*".chars().collect(),
+                    raw: None,
+                    span: Span::new(5, 11)
+                },
+            ])
         );
     }
 
     #[test]
     fn test_grouping_with_or_operator() {
-        let result = Lexer::with("name=(one|two|three)".to_string());
+        let result = Lexer::tokens("name=(one|two|three)");
         assert_eq!(
             result,
-            Ok(Lexer {
-                tokens: vec![
-                    Token {
-                        kind: Kind::Literal,
-                        value: "name".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![EQ]
-                    },
-                    Token {
-                        kind: Kind::Lparen,
-                        value: vec![LPAREN]
-                    },
-                    Token {
-                        kind: Kind::Literal,
-                        value: "one".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![OR]
-                    },
-                    Token {
-                        kind: Kind::Literal,
-                        value: "two".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![OR]
-                    },
-                    Token {
-                        kind: Kind::Literal,
-                        value: "three".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Rparen,
-                        value: vec![RPAREN]
-                    },
-                ],
-                index: 0
-            })
+            Ok(vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "name".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 4)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![EQ],
+                    raw: None,
+                    span: Span::new(4, 5)
+                },
+                Token {
+                    kind: Kind::Lparen,
+                    value: vec![LPAREN],
+                    raw: None,
+                    span: Span::new(5, 6)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "one".chars().collect(),
+                    raw: None,
+                    span: Span::new(6, 9)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![OR],
+                    raw: None,
+                    span: Span::new(9, 10)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "two".chars().collect(),
+                    raw: None,
+                    span: Span::new(10, 13)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![OR],
+                    raw: None,
+                    span: Span::new(13, 14)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "three".chars().collect(),
+                    raw: None,
+                    span: Span::new(14, 19)
+                },
+                Token {
+                    kind: Kind::Rparen,
+                    value: vec![RPAREN],
+                    raw: None,
+                    span: Span::new(19, 20)
+                },
+            ])
         );
     }
 
     #[test]
     fn test_grouping_with_and_operator() {
-        let result = Lexer::with("name=(one,two,three)".to_string());
+        let result = Lexer::tokens("name=(one,two,three)");
         assert_eq!(
             result,
-            Ok(Lexer {
-                tokens: vec![
-                    Token {
-                        kind: Kind::Literal,
-                        value: "name".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![EQ]
-                    },
-                    Token {
-                        kind: Kind::Lparen,
-                        value: vec![LPAREN]
-                    },
-                    Token {
-                        kind: Kind::Literal,
-                        value: "one".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![AND]
-                    },
-                    Token {
-                        kind: Kind::Literal,
-                        value: "two".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![AND]
-                    },
-                    Token {
-                        kind: Kind::Literal,
-                        value: "three".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Rparen,
-                        value: vec![RPAREN]
-                    },
-                ],
-                index: 0
-            })
+            Ok(vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "name".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 4)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![EQ],
+                    raw: None,
+                    span: Span::new(4, 5)
+                },
+                Token {
+                    kind: Kind::Lparen,
+                    value: vec![LPAREN],
+                    raw: None,
+                    span: Span::new(5, 6)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "one".chars().collect(),
+                    raw: None,
+                    span: Span::new(6, 9)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![AND],
+                    raw: None,
+                    span: Span::new(9, 10)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "two".chars().collect(),
+                    raw: None,
+                    span: Span::new(10, 13)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![AND],
+                    raw: None,
+                    span: Span::new(13, 14)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "three".chars().collect(),
+                    raw: None,
+                    span: Span::new(14, 19)
+                },
+                Token {
+                    kind: Kind::Rparen,
+                    value: vec![RPAREN],
+                    raw: None,
+                    span: Span::new(19, 20)
+                },
+            ])
         );
     }
 
     #[test]
     fn test_quoted() {
-        let result = Lexer::with("name:'elmer'".to_string());
+        let result = Lexer::tokens("name:'elmer'");
+        assert_eq!(
+            result,
+            Ok(vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "name".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 4)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COLON],
+                    raw: None,
+                    span: Span::new(4, 5)
+                },
+                Token {
+                    kind: Kind::String,
+                    value: "elmer".chars().collect(),
+                    raw: None,
+                    span: Span::new(5, 12)
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_lexer_stream() {
+        let filter = r"name='jim',tag=a\,b,x='";
+        let mut lexer = Lexer::new(filter);
+        assert_eq!(lexer.end(), 0);
+        let name = lexer.next().unwrap().unwrap();
+        assert!(matches!(name.value, Cow::Borrowed("name")));
+        assert_eq!(lexer.peek().unwrap().as_ref().unwrap().value, "=");
+        assert_eq!(
+            lexer.peek().unwrap().as_ref().unwrap().span,
+            Span::new(4, 5)
+        );
+        assert_eq!(lexer.next().unwrap().unwrap().value, "=");
+        let jim = lexer.next().unwrap().unwrap();
+        assert!(matches!(jim.value, Cow::Borrowed("jim")));
+        assert_eq!(jim.raw, "jim");
+        assert_eq!(lexer.end(), 10);
+        let values: Vec<String> = lexer
+            .by_ref()
+            .take(4)
+            .map(|t| t.unwrap().to_string())
+            .collect();
+        assert_eq!(values, vec![",", "tag", "=", "a,b"]);
+        assert_eq!(
+            lexer.nth(3),
+            Some(Err(FilterError::UnterminatedQuote {
+                quote: SQUOTE,
+                span: Span::new(22, 23)
+            }))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_separator() {
+        let result = Lexer::tokens("a=,|b").unwrap();
+        let values: Vec<String> = result.iter().map(|t| t.to_string()).collect();
+        assert_eq!(values, vec!["a", "=", ",", "|", "b"]);
+    }
+
+    #[test]
+    fn test_as_value() {
+        let values: Vec<TokenValue> =
+            Lexer::tokens("-1 4.5 1.0 5. 1.5 18446744073709551616 true 1.0.0 inf '7'")
+                .unwrap()
+                .iter()
+                .map(|t| t.as_value())
+                .collect();
+        assert_eq!(
+            values,
+            vec![
+                TokenValue::Int(-1),
+                TokenValue::Float(4.5),
+                TokenValue::Float(1.0),
+                TokenValue::Float(5.0),
+                TokenValue::Float(1.5),
+                TokenValue::Decimal(Decimal::parse("18446744073709551616").unwrap()),
+                TokenValue::Bool(true),
+                TokenValue::String("1.0.0".to_string()),
+                TokenValue::String("inf".to_string()),
+                TokenValue::String("7".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_as_value_time() {
+        let values: Vec<TokenValue> =
+            Lexer::tokens("2024-01-01 2024-05-01T10:00:00Z 7d 2024-02-30 '2024-01-01'")
+                .unwrap()
+                .iter()
+                .map(|t| t.as_value())
+                .collect();
+        assert_eq!(
+            values,
+            vec![
+                TokenValue::Date(Date::parse("2024-01-01").unwrap()),
+                TokenValue::DateTime(DateTime::parse("2024-05-01T10:00:00Z").unwrap()),
+                TokenValue::Duration(Duration { seconds: 604800 }),
+                TokenValue::String("2024-02-30".to_string()),
+                TokenValue::String("2024-01-01".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let result = Lexer::tokens("updated>=2024-05-01T10:00:00+05:30,at:'10:00'").unwrap();
         assert_eq!(
             result,
-            Ok(Lexer {
-                tokens: vec![
-                    Token {
-                        kind: Kind::Literal,
-                        value: "name".chars().collect()
-                    },
-                    Token {
-                        kind: Kind::Operator,
-                        value: vec![COLON]
-                    },
-                    Token {
-                        kind: Kind::String,
-                        value: "elmer".chars().collect()
-                    },
-                ],
-                index: 0
+            vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "updated".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 7)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: ">=".chars().collect(),
+                    raw: None,
+                    span: Span::new(7, 9)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "2024-05-01T10:00:00+05:30".chars().collect(),
+                    raw: None,
+                    span: Span::new(9, 34)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COMMA],
+                    raw: None,
+                    span: Span::new(34, 35)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "at".chars().collect(),
+                    raw: None,
+                    span: Span::new(35, 37)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COLON],
+                    raw: None,
+                    span: Span::new(37, 38)
+                },
+                Token {
+                    kind: Kind::String,
+                    value: "10:00".chars().collect(),
+                    raw: None,
+                    span: Span::new(38, 45)
+                },
+            ]
+        );
+        // (:) ends a field.
+        let values = |filter: &str| -> Vec<String> {
+            Lexer::tokens(filter)
+                .unwrap()
+                .iter()
+                .map(|t| t.value.iter().collect())
+                .collect()
+        };
+        assert_eq!(values("2024-05-01T10:x"), ["2024-05-01T10", ":", "x"]);
+        assert_eq!(
+            values("a=(1,2024-05-01T10:00),!2024-05-01T10:00"),
+            [
+                "a",
+                "=",
+                "(",
+                "1",
+                ",",
+                "2024-05-01T10:00",
+                ")",
+                ",",
+                "!",
+                "2024-05-01T10",
+                ":",
+                "00"
+            ]
+        );
+        assert_eq!(
+            values("(2024-05-01T10:1|b:2024-05-01T10:00)"),
+            [
+                "(",
+                "2024-05-01T10",
+                ":",
+                "1",
+                "|",
+                "b",
+                ":",
+                "2024-05-01T10:00",
+                ")"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quoted_escapes() {
+        let cases = [
+            (r#"''"#, ""),
+            (r#""""#, ""),
+            (r#"'it\'s'"#, "it's"),
+            (r#""say \"hi\"""#, "say \"hi\""),
+            (r#"'"double" in single'"#, "\"double\" in single"),
+            (r#"'C:\\Users\\elmer\\'"#, "C:\\Users\\elmer\\"),
+            (r#"'^\\d+$'"#, "^\\d+$"),
+            (r#"'C:\Users\elmer'"#, "C:\\Users\\elmer"),
+            (r#"'^\d+\s\w*$'"#, "^\\d+\\s\\w*$"),
+            (r#"'\u0041 \q'"#, "\\u0041 \\q"),
+            (r#"'a\nb\tc\r'"#, "a\nb\tc\r"),
+            (r#"'\u{e9}t\u{1F600}'"#, "\u{e9}t\u{1F600}"),
+        ];
+        for (filter, expected) in cases {
+            let result = Lexer::tokens(filter).unwrap();
+            assert_eq!(
+                result,
+                vec![Token {
+                    kind: Kind::String,
+                    value: expected.chars().collect(),
+                    raw: Some(filter[1..filter.len() - 1].chars().collect()),
+                    span: Span::new(0, filter.len())
+                }],
+                "{}",
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn test_quoted_errors() {
+        let cases = [
+            (
+                r#"a='\u{110000}'"#,
+                FilterError::InvalidEscape {
+                    sequence: r"\u{110000}".to_string(),
+                    span: Span::new(3, 13),
+                },
+            ),
+            (
+                r#"a='\u{41x}'"#,
+                FilterError::InvalidEscape {
+                    sequence: r"\u{41".to_string(),
+                    span: Span::new(3, 8),
+                },
+            ),
+            (
+                r#"a='\u{41"#,
+                FilterError::InvalidEscape {
+                    sequence: r"\u{41".to_string(),
+                    span: Span::new(3, 8),
+                },
+            ),
+            (
+                r#"a='x\'"#,
+                FilterError::UnterminatedQuote {
+                    quote: SQUOTE,
+                    span: Span::new(2, 6),
+                },
+            ),
+            (
+                r#"a='x\"#,
+                FilterError::UnterminatedQuote {
+                    quote: SQUOTE,
+                    span: Span::new(2, 5),
+                },
+            ),
+        ];
+        for (filter, expected) in cases {
+            assert_eq!(Lexer::tokens(filter), Err(expected), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_literal_escapes() {
+        let result = Lexer::tokens(r"tag=a\,b\ c,app\.name:\(x\)").unwrap();
+        assert_eq!(
+            result,
+            vec![
+                Token {
+                    kind: Kind::Literal,
+                    value: "tag".chars().collect(),
+                    raw: None,
+                    span: Span::new(0, 3)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![EQ],
+                    raw: None,
+                    span: Span::new(3, 4)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "a,b c".chars().collect(),
+                    raw: Some(r"a\,b\ c".chars().collect()),
+                    span: Span::new(4, 11)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COMMA],
+                    raw: None,
+                    span: Span::new(11, 12)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "app.name".chars().collect(),
+                    raw: Some(r"app\.name".chars().collect()),
+                    span: Span::new(12, 21)
+                },
+                Token {
+                    kind: Kind::Operator,
+                    value: vec![COLON],
+                    raw: None,
+                    span: Span::new(21, 22)
+                },
+                Token {
+                    kind: Kind::Literal,
+                    value: "(x)".chars().collect(),
+                    raw: Some(r"\(x\)".chars().collect()),
+                    span: Span::new(22, 27)
+                },
+            ]
+        );
+        // raw is kept only when it is not the value.
+        let tokens = Lexer::tokens(r"a=b\,c").unwrap();
+        assert_eq!(
+            (tokens[0].raw.as_ref(), tokens[0].raw()),
+            (None, &['a'][..])
+        );
+        assert_eq!(tokens[2].raw, Some(r"b\,c".chars().collect()));
+        assert_eq!(tokens[2].raw(), r"b\,c".chars().collect::<Vec<char>>());
+
+        // same sequences as quoted strings.
+        let value = |filter: &str| Lexer::tokens(filter).unwrap()[0].value.clone();
+        assert_eq!(value(r"a\d+"), r"a\d+".chars().collect::<Vec<char>>());
+        assert_eq!(value(r"a\nb"), "a\nb".chars().collect::<Vec<char>>());
+        assert_eq!(value(r"\u{41}"), vec!['A']);
+        assert_eq!(value(r"'a\,b\.c'"), vec!['a', ',', 'b', '.', 'c']);
+        assert_eq!(value(r"'a\d'"), value(r"a\d"));
+        assert_eq!(
+            unescape(&r"a\nb\d\.".chars().collect::<Vec<char>>()),
+            "a\nb\\d.".chars().collect::<Vec<char>>()
+        );
+        assert_eq!(
+            Lexer::tokens(r"a=\u{41"),
+            Err(FilterError::InvalidEscape {
+                sequence: r"\u{41".to_string(),
+                span: Span::new(2, 7)
+            })
+        );
+        assert_eq!(
+            Lexer::tokens(r"a=\u{41x}"),
+            Err(FilterError::InvalidEscape {
+                sequence: r"\u{41".to_string(),
+                span: Span::new(2, 7)
+            })
+        );
+        assert_eq!(
+            Lexer::tokens(r"a=\u{D800}"),
+            Err(FilterError::InvalidEscape {
+                sequence: r"\u{D800}".to_string(),
+                span: Span::new(2, 10)
+            })
+        );
+        assert_eq!(
+            Lexer::tokens(r"a=b\"),
+            Err(FilterError::InvalidEscape {
+                sequence: r"\".to_string(),
+                span: Span::new(3, 4)
             })
         );
     }
+
+    #[test]
+    fn test_query() {
+        let token = |filter: &str| Lexer::tokens(filter).unwrap()[0].clone();
+        assert_eq!(token(r"a\,b").query(), r"a\,b");
+        assert_eq!(token(r"a\b").query(), r"a\b");
+        assert_eq!(token("'a,b'").query(), "'a,b'");
+        let literal = |value: &str| Token {
+            kind: Kind::Literal,
+            value: value.chars().collect(),
+            raw: Some(vec![]),
+            span: Span::default(),
+        };
+        assert_eq!(literal("a,b=c").query(), r"a\,b\=c");
+        assert_eq!(literal(r"C:\x").query(), r"C\:\\x");
+        assert_eq!(literal("").query(), "''");
+        let escaped: Vec<char> = escape(&['a', ' ', '\\']).chars().collect();
+        assert_eq!(unescape(&escaped), vec!['a', ' ', '\\']);
+    }
 }
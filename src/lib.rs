@@ -0,0 +1,20 @@
+#[cfg(test)]
+mod arbitrary;
+pub mod builder;
+pub mod datetime;
+pub mod decimal;
+pub mod error;
+pub mod filter;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod lexer;
+pub mod limits;
+pub mod mapping;
+pub mod operator;
+pub mod parser;
+pub mod record;
+pub mod schema;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod sql;
+pub mod url;
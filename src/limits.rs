@@ -0,0 +1,90 @@
+use crate::error::{FilterError, Span};
+use std::fmt::{Display, Formatter};
+
+// Limits maxima enforced by Parser::filter_with() and Parser::expr_with().
+// Exceeding one is a LimitExceeded error. The default suits query
+// parameters and is used by Parser::filter() and Parser::expr().
+// Example:
+//   let limits = Limits {
+//       predicates: 10,
+//       ..Limits::default()
+//   };
+//   Parser::filter_with(filter, &limits)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Limits {
+    // Length of the filter in bytes.
+    pub input: usize,
+    // Number of predicates.
+    pub predicates: usize,
+    // Number of items in a list.
+    pub list: usize,
+    // Length of a token in bytes, including quotes and escapes.
+    pub token: usize,
+    // Nesting of groups and negations. Example: !(a=1) has depth 2.
+    pub depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            input: 4096,
+            predicates: 64,
+            list: 256,
+            token: 512,
+            depth: 16,
+        }
+    }
+}
+
+impl Limits {
+    // Unlimited limits; for trusted filters.
+    // The depth is still capped by parser::MAX_DEPTH.
+    pub fn unlimited() -> Self {
+        Self {
+            input: usize::MAX,
+            predicates: usize::MAX,
+            list: usize::MAX,
+            token: usize::MAX,
+            depth: usize::MAX,
+        }
+    }
+
+    // check the count against the limit.
+    pub(crate) fn check(&self, limit: Limit, count: usize, span: Span) -> Result<(), FilterError> {
+        let max = match limit {
+            Limit::Input => self.input,
+            Limit::Predicates => self.predicates,
+            Limit::List => self.list,
+            Limit::Token => self.token,
+            Limit::Depth => self.depth,
+        };
+        if count > max {
+            Err(FilterError::LimitExceeded { limit, max, span })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Limit exceeded. See: Limits.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Limit {
+    Input,
+    Predicates,
+    List,
+    Token,
+    Depth,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Limit::Input => "filter length",
+            Limit::Predicates => "predicates",
+            Limit::List => "list items",
+            Limit::Token => "token length",
+            Limit::Depth => "nesting depth",
+        };
+        write!(f, "{}", s)
+    }
+}
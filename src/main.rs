@@ -1,91 +1,460 @@
-use crate::lexer::{Kind, Token};
-use crate::parser::{Parser, Value};
+use filters::error::FilterError;
+use filters::lexer::TokenValue;
+use filters::parser::{Expr, Parser, Predicate};
+use filters::schema::Schema;
+use filters::sql::{Placeholder, Sql};
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
 
-mod filter;
-mod lexer;
-mod parser;
+const USAGE: &str = "\
+Usage: filters <command> [options] [query...]
 
-fn print_tokens(tokens: Vec<Token>) -> String {
-    tokens
-        .iter()
-        .map(|v| format!("{}", v.as_value()))
-        .collect::<Vec<_>>()
-        .join(" AND ")
+Commands:
+  parse    Print the parsed filter as a table or JSON.
+  check    Validate the filter against a schema.
+  sql      Print the SQL WHERE clause and bind values.
+  fmt      Print the canonical filter.
+
+Options:
+  --flat                 Parse as Parser::filter(): no `|`, `!` or groups.
+  --format <table|json>  Output of parse (default: table).
+  --schema <file>        JSON schema; required by check, types sql values.
+  --placeholder <style>  SQL placeholders: question (default), numbered, named.
+  -h, --help             Print this help.
+
+The query is the arguments joined by spaces; without a query, each line
+of stdin is a query. Exits 1 when a query is not valid, 2 on usage or
+output errors.
+";
+
+// Command subcommand.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Command {
+    Parse,
+    Check,
+    Sql,
+    Fmt,
 }
 
-fn print_value(value: Value) -> String {
-    let tokens = value.by_kind(vec![Kind::Literal, Kind::String]);
-    print_tokens(tokens)
+// Format output of parse.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Format {
+    Table,
+    Json,
 }
 
-fn print_value_operator(value: Value) -> String {
-    let tokens = value.by_kind(vec![Kind::Operator]);
-    print_tokens(tokens)
+// Failure of a command.
+#[derive(Debug)]
+enum Failure {
+    // The query is not valid; exits 1.
+    Invalid(Vec<FilterError>),
+    // The output cannot be written; exits 2.
+    Output(String),
 }
 
-fn print_line() {
-    println!(
-        "--------------------------------------------------------------------------------------------------------------------------------------------"
-    )
+impl From<Vec<FilterError>> for Failure {
+    fn from(errors: Vec<FilterError>) -> Self {
+        Failure::Invalid(errors)
+    }
 }
 
-fn main() {
-    print_line();
-    let parser = Parser::filter("filterText~'special characters like =<>~', age>=18, name=(jim|crossley), address='single quote address', nickname=\"use double quotes\", labels=('kubernetes.io/part-of: trustify', 'kubernetes.io/part-of: operator')").unwrap();
+// Options command line.
+#[derive(Debug)]
+struct Options {
+    command: Command,
+    flat: bool,
+    format: Format,
+    schema: Option<Schema>,
+    placeholder: Placeholder,
+    query: Option<String>,
+}
 
-    let field_filter_text = parser.field("filterText").unwrap();
-    println!(
-        "FIELD:'{}'\t OPERATOR:'{}'\t VALUE:'{}'",
-        field_filter_text.name(),
-        field_filter_text.operator(),
-        print_value(field_filter_text.value()),
-    );
-    print_line();
-
-    let field_age = parser.field("age").unwrap();
-    println!(
-        "FIELD:'{}'\t\t OPERATOR:'{}'\t VALUE:'{}'",
-        field_age.name(),
-        field_age.operator(),
-        print_value(field_age.value()),
-    );
-    print_line();
-
-    let field_name = parser.field("name").unwrap();
-    println!(
-        "FIELD:'{}'\t\t OPERATOR:'{}'\t VALUE:'{}'\t\t\t\t\t\t\t VALUE_OPERATOR:'{}'",
-        field_name.name(),
-        field_name.operator(),
-        print_value(field_name.value()),
-        print_value_operator(field_name.value()),
-    );
-    print_line();
-
-    let field_address = parser.field("address").unwrap();
-    println!(
-        "FIELD:'{}'\t\t OPERATOR:'{}'\t VALUE:'{}'\t\t\t",
-        field_address.name(),
-        field_address.operator(),
-        print_value(field_address.value()),
-    );
-    print_line();
-
-    let field_nickname = parser.field("nickname").unwrap();
-    println!(
-        "FIELD:'{}'\t OPERATOR:'{}'\t VALUE:'{}'",
-        field_nickname.name(),
-        field_nickname.operator(),
-        print_value(field_nickname.value()),
-    );
-    print_line();
-
-    let field_labels = parser.field("labels").unwrap();
-    println!(
-        "FIELD:'{}'\t\t OPERATOR:'{}'\t VALUE:'{}'\t VALUE_OPERATOR:'{}'",
-        field_labels.name(),
-        field_labels.operator(),
-        print_value(field_labels.value()),
-        print_value_operator(field_labels.value()),
+impl Options {
+    // Parse the arguments (without the program name).
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut args = args.iter();
+        let command = match args.next().map(String::as_str) {
+            Some("parse") => Command::Parse,
+            Some("check") => Command::Check,
+            Some("sql") => Command::Sql,
+            Some("fmt") => Command::Fmt,
+            Some(other) => return Err(format!("unknown command `{}`", other)),
+            None => return Err("missing command".to_string()),
+        };
+        let mut options = Options {
+            command,
+            flat: false,
+            format: Format::Table,
+            schema: None,
+            placeholder: Placeholder::Question,
+            query: None,
+        };
+        let mut words: Vec<&str> = vec![];
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--flat" => options.flat = true,
+                "--format" => {
+                    options.format = match value(&mut args, arg)? {
+                        "table" => Format::Table,
+                        "json" => Format::Json,
+                        other => return Err(format!("unknown format `{}`", other)),
+                    }
+                }
+                "--schema" => options.schema = Some(schema(value(&mut args, arg)?)?),
+                "--placeholder" => {
+                    options.placeholder = match value(&mut args, arg)? {
+                        "question" => Placeholder::Question,
+                        "numbered" => Placeholder::Numbered,
+                        "named" => Placeholder::Named,
+                        other => return Err(format!("unknown placeholder `{}`", other)),
+                    }
+                }
+                // the rest is the query. Example: filters fmt -- --x=1
+                "--" => {
+                    words.extend(args.by_ref().map(String::as_str));
+                }
+                other if other.starts_with("--") => {
+                    return Err(format!("unknown option `{}`", other));
+                }
+                word => words.push(word),
+            }
+        }
+        if options.command == Command::Check && options.schema.is_none() {
+            return Err("check requires --schema".to_string());
+        }
+        if !words.is_empty() {
+            options.query = Some(words.join(" "));
+        }
+        Ok(options)
+    }
+
+    // Execute the command for the query; returns the output.
+    fn execute(&self, query: &str) -> Result<String, Failure> {
+        let expr = if self.flat {
+            Parser::filter(query).map(|f| f.expr())
+        } else {
+            Parser::expr(query)
+        }
+        .map_err(|err| vec![err])?;
+        match self.command {
+            Command::Parse => Ok(match self.format {
+                Format::Table => table(&expr),
+                Format::Json => json(&expr, self.flat).map_err(Failure::Output)?,
+            }),
+            Command::Check => {
+                if let Some(schema) = &self.schema {
+                    expr.validate(schema)?;
+                }
+                Ok("ok\n".to_string())
+            }
+            Command::Sql => {
+                let mut sql = Sql::new(self.placeholder);
+                if let Some(schema) = &self.schema {
+                    sql = sql.schema(schema.clone());
+                }
+                let clause = sql.expr(&expr).map_err(|err| vec![err])?;
+                let mut output = format!("{}\n", clause.clause);
+                for bind in clause.binds.iter() {
+                    output.push_str(&format!(
+                        "{} = {} ({})\n",
+                        bind.name,
+                        bind.value,
+                        type_name(&bind.value)
+                    ));
+                }
+                Ok(output)
+            }
+            Command::Fmt => Ok(format!("{}\n", expr)),
+        }
+    }
+}
+
+// value of the option.
+fn value<'a>(args: &mut impl Iterator<Item = &'a String>, option: &str) -> Result<&'a str, String> {
+    args.next()
+        .map(String::as_str)
+        .ok_or_else(|| format!("missing value for `{}`", option))
+}
+
+// schema read from the JSON file.
+fn schema(path: &str) -> Result<Schema, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|err| format!("cannot read `{}`: {}", path, err))?;
+    serde_json::from_str(&text).map_err(|err| format!("invalid schema `{}`: {}", path, err))
+}
+
+// json of the expression; the flat view is the list of predicates.
+fn json(expr: &Expr, flat: bool) -> Result<String, String> {
+    let json = match expr.filter() {
+        Some(filter) if flat => serde_json::to_string_pretty(&filter),
+        _ => serde_json::to_string_pretty(expr),
+    };
+    json.map(|json| format!("{}\n", json))
+        .map_err(|err| format!("cannot write JSON: {}", err))
+}
+
+// table of the predicates.
+// The group is the path of and/or/not enclosing the predicate.
+fn table(expr: &Expr) -> String {
+    let mut rows: Vec<Vec<String>> = vec![
+        ["GROUP", "FIELD", "OP", "VALUE", "TYPE", "SPAN"]
+            .map(String::from)
+            .to_vec(),
+    ];
+    collect(expr, "", &mut rows);
+
+    let mut widths = vec![0; rows[0].len()];
+    for row in rows.iter() {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+    let mut output = String::new();
+    for row in rows.iter() {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            line.push_str(&format!("{:width$}  ", cell, width = widths[i]));
+        }
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+// collect the rows of the expression.
+fn collect(expr: &Expr, group: &str, rows: &mut Vec<Vec<String>>) {
+    let path = |name: &str| match group {
+        "" => name.to_string(),
+        _ => format!("{}/{}", group, name),
+    };
+    match expr {
+        Expr::And(items) => items.iter().for_each(|e| collect(e, &path("and"), rows)),
+        Expr::Or(items) => items.iter().for_each(|e| collect(e, &path("or"), rows)),
+        Expr::Not(item) => collect(item, &path("not"), rows),
+        Expr::Predicate(p) => rows.push(vec![
+            if group.is_empty() { "-" } else { group }.to_string(),
+            p.field.field_query(),
+            p.operator.to_string(),
+            p.value.to_string(),
+            types(p),
+            p.span.to_string(),
+        ]),
+    }
+}
+
+// types guessed from the value; distinct, in order.
+fn types(p: &Predicate) -> String {
+    let mut names: Vec<&str> = vec![];
+    for token in p.value.0.iter().step_by(2) {
+        let name = type_name(&token.as_value());
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names.join(",")
+}
+
+fn type_name(value: &TokenValue) -> &'static str {
+    match value {
+        TokenValue::String(_) => "string",
+        TokenValue::Int(_) => "int",
+        TokenValue::Float(_) => "float",
+        TokenValue::Decimal(_) => "decimal",
+        TokenValue::Bool(_) => "bool",
+        TokenValue::Date(_) => "date",
+        TokenValue::DateTime(_) => "datetime",
+        TokenValue::Duration(_) => "duration",
+    }
+}
+
+// run the command line; returns the exit code.
+fn run(
+    args: &[String],
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> io::Result<u8> {
+    if args.is_empty() {
+        write!(err, "{}", USAGE)?;
+        return Ok(2);
+    }
+    // options end at `--`; the rest is the query.
+    let options = args.iter().take_while(|arg| *arg != "--");
+    if options
+        .into_iter()
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        write!(out, "{}", USAGE)?;
+        return Ok(0);
+    }
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(message) => {
+            writeln!(err, "error: {}\n\n{}", message, USAGE)?;
+            return Ok(2);
+        }
+    };
+    let queries: Vec<String> = match &options.query {
+        Some(query) => vec![query.clone()],
+        None => input
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .collect::<io::Result<_>>()?,
+    };
+
+    let mut code = 0;
+    for query in queries.iter() {
+        match options.execute(query) {
+            Ok(output) => write!(out, "{}", output)?,
+            Err(Failure::Invalid(errors)) => {
+                for error in errors.iter() {
+                    writeln!(err, "{}", error.render(query))?;
+                }
+                code = 1;
+            }
+            Err(Failure::Output(message)) => {
+                writeln!(err, "error: {}", message)?;
+                return Ok(2);
+            }
+        }
+    }
+    Ok(code)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let code = run(
+        &args,
+        &mut io::stdin().lock(),
+        &mut io::stdout().lock(),
+        &mut io::stderr().lock(),
     );
-    print_line();
+    match code {
+        Ok(code) => ExitCode::from(code),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // run the command line with the input; returns (code, out, err).
+    fn cli(args: &[&str], input: &str) -> (u8, String, String) {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let mut out: Vec<u8> = vec![];
+        let mut err: Vec<u8> = vec![];
+        let code = run(&args, &mut input.as_bytes(), &mut out, &mut err).unwrap();
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_cli_fmt() {
+        let (code, out, _) = cli(&["fmt", "name = jim ,", "age>=18"], "");
+        assert_eq!((code, out.as_str()), (0, "name=jim,age>=18\n"));
+
+        let (code, out, err) = cli(&["fmt"], "a = 1 | b=2\n\nname=(x|y,z)\n");
+        assert_eq!(code, 1);
+        assert_eq!(out, "a=1|b=2\n");
+        assert_eq!(
+            err,
+            "name=(x|y,z)\n         ^ mixed operators `|` and `,` in list\n"
+        );
+
+        let (code, _, err) = cli(&["fmt", "--flat", "a=1|b=2"], "");
+        assert_eq!(code, 1);
+        assert!(err.contains("unexpected `|`"));
+    }
+
+    #[test]
+    fn test_cli_parse() {
+        let (code, out, _) = cli(&["parse", "name=(jim|crossley),!(age>18.5)"], "");
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "\
+GROUP    FIELD  OP  VALUE           TYPE    SPAN
+and      name   =   (jim|crossley)  string  0..19
+and/not  age    >   18.5            float   22..30
+"
+        );
+
+        let (code, out, _) = cli(&["parse", "--format", "json", "--flat", "age>=18"], "");
+        assert_eq!(code, 0);
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!([{"field": "age", "op": ">=", "value": 18}])
+        );
+    }
+
+    #[test]
+    fn test_cli_check() {
+        let path = std::env::temp_dir().join(format!("filters-schema-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"fields": [{"name": "name", "type": "string"}, {"name": "age", "type": "integer"}]}"#,
+        )
+        .unwrap();
+        let schema = path.to_str().unwrap();
+
+        let (code, out, _) = cli(&["check", "--schema", schema, "name~jim*,age>18"], "");
+        assert_eq!((code, out.as_str()), (0, "ok\n"));
+
+        let (code, _, err) = cli(&["check", "--schema", schema, "nam=jim,age>x"], "");
+        assert_eq!(code, 1);
+        assert!(err.contains("unknown field `nam`, did you mean `name`?"));
+        assert!(err.contains("invalid value `x` for field `age`, expected integer"));
+
+        let (code, _, err) = cli(&["sql", "--schema", schema, "age>18"], "");
+        assert_eq!((code, err.as_str()), (0, ""));
+        std::fs::remove_file(&path).unwrap();
+
+        let (code, _, err) = cli(&["check", "name=jim"], "");
+        assert_eq!(code, 2);
+        assert!(err.starts_with("error: check requires --schema"));
+    }
+
+    #[test]
+    fn test_cli_sql() {
+        let (code, out, _) = cli(
+            &["sql", "--placeholder", "numbered", "name=(jim|bob),age>=18"],
+            "",
+        );
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "name IN ($1, $2) AND age >= $3\n$1 = jim (string)\n$2 = bob (string)\n$3 = 18 (int)\n"
+        );
+    }
+
+    #[test]
+    fn test_cli_usage() {
+        assert_eq!(cli(&[], "").0, 2);
+        let (code, out, _) = cli(&["parse", "--help"], "");
+        assert_eq!(code, 0);
+        assert!(out.starts_with("Usage: filters"));
+        let (code, out, _) = cli(&["fmt", "--", "--help=1"], "");
+        assert_eq!((code, out.as_str()), (0, "--help=1\n"));
+        let (code, out, err) = cli(&["fmt", "--", "-h"], "");
+        assert_eq!((code, out.as_str()), (1, ""));
+        assert!(!err.contains("Usage"));
+        for args in [
+            &["lint", "a=1"][..],
+            &["parse", "--format", "xml", "a=1"],
+            &["parse", "--format"],
+            &["sql", "--dialect", "pg"],
+        ] {
+            let (code, _, err) = cli(args, "");
+            assert_eq!(code, 2, "{:?}", args);
+            assert!(err.starts_with("error: "), "{:?}", args);
+        }
+    }
 }
@@ -0,0 +1,187 @@
+use crate::error::FilterError;
+use crate::filter::{Field, Filter};
+use crate::lexer::{Kind, Token};
+use crate::parser::{Expr, Predicate};
+
+// Mapping translates client field names to internal names.
+// Names are matched case-insensitive. A field is mapped by:
+//   - the field name. Example: name -> users.full_name
+//   - the resource. Example: app -> applications (app.name -> applications.name)
+// Unmapped fields are reported as errors.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Mapping {
+    pub fields: Vec<(String, String)>,
+    pub resources: Vec<(String, String)>,
+}
+
+impl Mapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Field maps the field name.
+    pub fn field(mut self, name: &str, internal: &str) -> Self {
+        self.fields.push((name.to_string(), internal.to_string()));
+        self
+    }
+
+    // Resource maps the resource of qualified fields.
+    pub fn resource(mut self, name: &str, internal: &str) -> Self {
+        self.resources
+            .push((name.to_string(), internal.to_string()));
+        self
+    }
+
+    // Get returns the internal name for the field.
+    pub fn get(&self, field: &Field) -> Option<String> {
+        let name = field.predicate.field.to_string().to_lowercase();
+        if let Some((_, internal)) = self.fields.iter().find(|(n, _)| n.to_lowercase() == name) {
+            return Some(internal.clone());
+        }
+        let (resource, name) = field.split();
+        let resource = resource?.to_lowercase();
+        self.resources
+            .iter()
+            .find(|(n, _)| n.to_lowercase() == resource)
+            .map(|(_, internal)| format!("{}.{}", internal, name))
+    }
+
+    // Filter returns the filter with internal field names.
+    pub fn filter(&self, filter: &Filter) -> Result<Filter, Vec<FilterError>> {
+        let mut predicates: Vec<Predicate> = vec![];
+        let mut errors: Vec<FilterError> = vec![];
+        for p in filter.predicates.iter() {
+            match self.predicate(p) {
+                Ok(p) => predicates.push(p),
+                Err(err) => errors.push(err),
+            }
+        }
+        if errors.is_empty() {
+            Ok(Filter { predicates })
+        } else {
+            Err(errors)
+        }
+    }
+
+    // Expr returns the expression with internal field names.
+    pub fn expr(&self, expr: &Expr) -> Result<Expr, Vec<FilterError>> {
+        let mut errors: Vec<FilterError> = vec![];
+        let expr = self.map(expr, &mut errors);
+        if errors.is_empty() {
+            Ok(expr)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // map the expression collecting errors.
+    fn map(&self, expr: &Expr, errors: &mut Vec<FilterError>) -> Expr {
+        match expr {
+            Expr::And(items) => Expr::And(items.iter().map(|e| self.map(e, errors)).collect()),
+            Expr::Or(items) => Expr::Or(items.iter().map(|e| self.map(e, errors)).collect()),
+            Expr::Not(item) => Expr::Not(Box::new(self.map(item, errors))),
+            Expr::Predicate(p) => match self.predicate(p) {
+                Ok(p) => Expr::Predicate(p),
+                Err(err) => {
+                    errors.push(err);
+                    Expr::Predicate(p.clone())
+                }
+            },
+        }
+    }
+
+    // predicate with the internal field name.
+    // The span still refers to the client filter.
+    fn predicate(&self, p: &Predicate) -> Result<Predicate, FilterError> {
+        let field = Field { predicate: p };
+        match self.get(&field) {
+            Some(internal) => Ok(Predicate {
+                field: Token {
+                    kind: Kind::Literal,
+                    value: internal.chars().collect(),
+                    raw: None,
+                    span: p.field.span,
+                },
+                operator: p.operator,
+                value: p.value.clone(),
+                span: p.span,
+            }),
+            None => Err(FilterError::UnknownField {
+                name: p.field.to_string(),
+                suggestion: None,
+                span: p.field.span,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Span;
+    use crate::parser::Parser;
+
+    fn mapping() -> Mapping {
+        Mapping::new()
+            .field("name", "users.full_name")
+            .field("vulnerabilities", "cve_count")
+            .field("app.id", "applications.uuid")
+            .resource("app", "applications")
+    }
+
+    #[test]
+    fn test_mapping_filter() {
+        let p = Parser::filter("Name=jim,vulnerabilities>10,app.name=x,APP.id=1").unwrap();
+        let mapped = mapping().filter(&p).unwrap();
+        let fields: Vec<String> = mapped
+            .predicates
+            .iter()
+            .map(|p| p.field.to_string())
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "users.full_name",
+                "cve_count",
+                "applications.name",
+                "applications.uuid"
+            ]
+        );
+        let users = mapped.resource("users");
+        let field = users.field("full_name").unwrap();
+        assert_eq!(field.span(), Span::new(0, 8));
+    }
+
+    #[test]
+    fn test_mapping_unmapped() {
+        let p = Parser::filter("name=jim,age>1,user.id=2").unwrap();
+        assert_eq!(
+            mapping().filter(&p),
+            Err(vec![
+                FilterError::UnknownField {
+                    name: "age".to_string(),
+                    suggestion: None,
+                    span: Span::new(9, 12)
+                },
+                FilterError::UnknownField {
+                    name: "user.id".to_string(),
+                    suggestion: None,
+                    span: Span::new(15, 22)
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_mapping_expr() {
+        let p = Parser::expr("!(name=jim|app.tag=x)").unwrap();
+        let mapped = mapping().expr(&p).unwrap();
+        let fields: Vec<String> = mapped
+            .predicates()
+            .iter()
+            .map(|p| p.field.to_string())
+            .collect();
+        assert_eq!(fields, vec!["users.full_name", "applications.tag"]);
+        assert!(matches!(mapped, Expr::Not(_)));
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt::{Display, Formatter};
+
+// CompareOp predicate (comparison) operator.
+// List values (a|b) and (a,b) match any and all items.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum CompareOp {
+    // `=`
+    Eq,
+    // `!=`
+    NotEq,
+    // `<`
+    Lt,
+    // `<=`
+    Le,
+    // `>`
+    Gt,
+    // `>=`
+    Ge,
+    // `~` with `*` wildcards.
+    Like,
+    // `!~`
+    NotLike,
+    // `:` field has the value.
+    // Same as `=`; collections match any element.
+    Has,
+}
+
+impl CompareOp {
+    // All operators.
+    pub const ALL: [CompareOp; 9] = [
+        CompareOp::Eq,
+        CompareOp::NotEq,
+        CompareOp::Lt,
+        CompareOp::Le,
+        CompareOp::Gt,
+        CompareOp::Ge,
+        CompareOp::Like,
+        CompareOp::NotLike,
+        CompareOp::Has,
+    ];
+
+    // Parse returns the operator for the characters.
+    // Returns None when the operator is not known.
+    pub fn parse(s: &str) -> Option<CompareOp> {
+        CompareOp::ALL.into_iter().find(|op| op.as_str() == s)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompareOp::Eq => "=",
+            CompareOp::NotEq => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+            CompareOp::Like => "~",
+            CompareOp::NotLike => "!~",
+            CompareOp::Has => ":",
+        }
+    }
+
+    // Negated returns true for `!=` and `!~`.
+    pub fn negated(&self) -> bool {
+        matches!(self, CompareOp::NotEq | CompareOp::NotLike)
+    }
+
+    // Negate returns the operator matching the opposite.
+    // The opposite of `:` is `!=`.
+    pub fn negate(&self) -> CompareOp {
+        match self {
+            CompareOp::Eq | CompareOp::Has => CompareOp::NotEq,
+            CompareOp::NotEq => CompareOp::Eq,
+            CompareOp::Lt => CompareOp::Ge,
+            CompareOp::Le => CompareOp::Gt,
+            CompareOp::Gt => CompareOp::Le,
+            CompareOp::Ge => CompareOp::Lt,
+            CompareOp::Like => CompareOp::NotLike,
+            CompareOp::NotLike => CompareOp::Like,
+        }
+    }
+}
+
+impl Display for CompareOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{FilterError, Span};
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_operator_parse() {
+        for op in CompareOp::ALL {
+            assert_eq!(CompareOp::parse(op.as_str()), Some(op));
+        }
+        assert_eq!(CompareOp::parse("=<>~"), None);
+        assert_eq!(CompareOp::parse("::"), None);
+        assert_eq!(CompareOp::parse(""), None);
+    }
+
+    #[test]
+    fn test_operator_negate() {
+        for op in CompareOp::ALL {
+            let expected = if op == CompareOp::Has {
+                CompareOp::Eq
+            } else {
+                op
+            };
+            assert_eq!(op.negate().negate(), expected);
+        }
+        assert_eq!(CompareOp::Ge.negate(), CompareOp::Lt);
+    }
+
+    #[test]
+    fn test_operator_unknown() {
+        assert_eq!(
+            Parser::filter("name=elmer,age=<>~20"),
+            Err(FilterError::UnknownOperator {
+                operator: "=<>~".to_string(),
+                span: Span::new(14, 18)
+            })
+        );
+        assert_eq!(
+            Parser::filter("name::elmer"),
+            Err(FilterError::UnknownOperator {
+                operator: "::".to_string(),
+                span: Span::new(4, 6)
+            })
+        );
+        let p = Parser::filter("a:1,b=2,c!=3,d<4,e<=5,f>6,g>=7,h~8,i!~9").unwrap();
+        let operators: Vec<CompareOp> = p.predicates.iter().map(|p| p.operator).collect();
+        assert_eq!(
+            operators,
+            vec![
+                CompareOp::Has,
+                CompareOp::Eq,
+                CompareOp::NotEq,
+                CompareOp::Lt,
+                CompareOp::Le,
+                CompareOp::Gt,
+                CompareOp::Ge,
+                CompareOp::Like,
+                CompareOp::NotLike,
+            ]
+        );
+    }
+}
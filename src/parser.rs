@@ -1,206 +1,519 @@
-use crate::lexer::{AND, Kind, OR};
-use crate::{
-    filter::Filter,
-    lexer::{COMMA, Lexer, Token},
-};
+use crate::error::{FilterError, Span};
+use crate::filter::Filter;
+use crate::lexer::{AND, Kind, Lexeme, Lexer, NOT, OR, Token};
+use crate::limits::{Limit, Limits};
+use crate::operator::CompareOp;
+use std::fmt::{Display, Formatter};
 
+// Parser recursive-descent filter parser.
+// One token of lookahead.
+//
+// Grammar (EBNF):
+//
+//   filter    = [ expr ] ;
+//   expr      = term { "," term } | term { "|" term } ;
+//   term      = "!" term | "(" expr ")" | predicate ;
+//   predicate = field operator value ;
+//   field     = LITERAL | STRING ;
+//   operator  = "=" | "!=" | "<" | "<=" | ">" | ">=" | "~" | "!~" | ":" ;
+//   value     = LITERAL | STRING | list ;
+//   list      = "(" item { "," item } ")" | "(" item { "|" item } ")" ;
+//   item      = LITERAL | STRING ;
+//   LITERAL   = ( char | escape ) { char | escape } ;
+//   STRING    = "'" { char | escape } "'" | '"' { char | escape } '"' ;
+//   escape    = "\" ( "n" | "t" | "r" | "u{" hex { hex } "}" | special ) ;
+//   special   = "'" | '"' | "\" | " " | "(" | ")" | "," | "|"
+//             | "=" | "~" | "!" | "<" | ">" | ":" | "." ;
+//
+// A LITERAL char is not special; STRING chars are not the quote.
+// In a value, (:) continues a timestamp. Example: at>2024-05-01T10:00
+// Other (\) sequences are kept as written. Example: C:\Users
+//
+// Mixing `,` and `|` at the same level requires grouping with ().
+// A term prefixed with `!` is negated. Example: !(name=jim|age>30)
+// Parser::filter() accepts only `,` between terms (flat view).
+// Parser::filter() and Parser::expr() apply the default limits. See: Limits.
 pub struct Parser;
 
+// MaxDepth nesting of groups and negations accepted whatever the limits.
+// Each level recurses; deeper filters would overflow the stack.
+pub const MAX_DEPTH: usize = 128;
+
 impl Parser {
-    pub fn filter(filter: &str) -> Result<Filter, String> {
-        if filter.is_empty() {
-            return Ok(Filter { predicates: vec![] });
+    // Expr parses the filter into a boolean expression within the
+    // default limits. An empty filter is an empty And (matches everything).
+    pub fn expr(filter: &str) -> Result<Expr, FilterError> {
+        Self::expr_with(filter, &Limits::default())
+    }
+
+    // Filter parses the filter into the flat (ANDed) view within the
+    // default limits.
+    pub fn filter(filter: &str) -> Result<Filter, FilterError> {
+        Self::filter_with(filter, &Limits::default())
+    }
+
+    // ExprWith parses the filter within the limits.
+    pub fn expr_with(filter: &str, limits: &Limits) -> Result<Expr, FilterError> {
+        Self::parse(filter, false, limits)
+    }
+
+    // FilterWith parses the filter within the limits.
+    // Example: Parser::filter_with(filter, &Limits::default())
+    pub fn filter_with(filter: &str, limits: &Limits) -> Result<Filter, FilterError> {
+        let expr = Self::parse(filter, true, limits)?;
+        Ok(Filter {
+            predicates: expr.into_predicates(),
+        })
+    }
+
+    // parse the filter.
+    fn parse(filter: &str, flat: bool, limits: &Limits) -> Result<Expr, FilterError> {
+        // checked before scanning.
+        let start = filter.floor_char_boundary(limits.input.min(filter.len()));
+        limits.check(Limit::Input, filter.len(), Span::new(start, filter.len()))?;
+
+        let mut lexer = Lexer::new(filter);
+        if lexer.peek().is_none() {
+            return Ok(Expr::And(vec![]));
+        }
+
+        let mut expression = Expression {
+            lexer: &mut lexer,
+            flat,
+            limits,
+            predicates: 0,
+            depth: 0,
+        };
+        let expr = expression.build()?;
+        match next(&mut lexer, limits) {
+            Some(token) => Err(unexpected(&token?)),
+            None => Ok(expr),
+        }
+    }
+}
+
+// next token within the token limit.
+fn next<'a>(lexer: &mut Lexer<'a>, limits: &Limits) -> Option<Result<Lexeme<'a>, FilterError>> {
+    let token = match lexer.next()? {
+        Ok(token) => token,
+        Err(err) => return Some(Err(err)),
+    };
+    let len = token.span.end - token.span.start;
+    Some(limits.check(Limit::Token, len, token.span).map(|_| token))
+}
+
+// eof returns the (empty) span at the end of the scanned tokens.
+fn eof(lexer: &Lexer) -> Span {
+    Span::new(lexer.end(), lexer.end())
+}
+
+// unexpected token error.
+fn unexpected(token: &Lexeme) -> FilterError {
+    FilterError::UnexpectedToken {
+        found: token.to_string(),
+        span: token.span,
+    }
+}
+
+// Expr boolean expression.
+// Example: (name=jim|age>30),status=active
+// Only the top-level And may be empty (the empty filter); an empty
+// group has no query syntax. Deserialized expressions are checked.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(rename_all = "lowercase", try_from = "crate::serialize::ExprJson")
+)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    #[cfg_attr(feature = "serde", serde(untagged))]
+    Predicate(Predicate),
+}
+
+impl Expr {
+    // Predicates returns all predicates in the expression.
+    pub fn predicates(&self) -> Vec<&Predicate> {
+        let mut predicates: Vec<&Predicate> = vec![];
+        match self {
+            Expr::And(items) | Expr::Or(items) => {
+                for item in items.iter() {
+                    predicates.extend(item.predicates());
+                }
+            }
+            Expr::Not(item) => predicates.extend(item.predicates()),
+            Expr::Predicate(p) => predicates.push(p),
         }
+        predicates
+    }
 
+    // into_predicates moves the predicates out of the expression.
+    fn into_predicates(self) -> Vec<Predicate> {
         let mut predicates: Vec<Predicate> = vec![];
+        let mut pending: Vec<Expr> = vec![self];
+        while let Some(expr) = pending.pop() {
+            match expr {
+                Expr::And(items) | Expr::Or(items) => pending.extend(items.into_iter().rev()),
+                Expr::Not(item) => pending.push(*item),
+                Expr::Predicate(p) => predicates.push(p),
+            }
+        }
+        predicates
+    }
 
-        let mut lexer = Lexer::with(format!("{}{}", COMMA, filter))?;
-        let mut brf: Vec<Token> = vec![];
-        loop {
-            if let Some(token) = &lexer.next() {
-                if brf.len() > 2 {
-                    match (brf.first(), brf.get(1), brf.get(2)) {
-                        (Some(first), Some(second), Some(third)) => {
-                            match (&first.kind, &second.kind, &third.kind) {
-                                (Kind::Operator, _, Kind::Operator) => match token.kind {
-                                    Kind::Literal | Kind::String => {
-                                        let p = Predicate {
-                                            unused: first.clone(),
-                                            field: second.clone(),
-                                            operator: third.clone(),
-                                            value: Value(vec![token.clone()]),
-                                        };
-                                        predicates.push(p);
-                                        brf.clear();
-                                    }
-                                    Kind::Lparen => {
-                                        lexer.put();
-                                        let mut list = List { lexer: &mut lexer };
-                                        let v = list.build()?;
-                                        let p = Predicate {
-                                            unused: first.clone(),
-                                            field: second.clone(),
-                                            operator: third.clone(),
-                                            value: v,
-                                        };
-                                        predicates.push(p);
-                                        brf.clear();
-                                    }
-                                    Kind::Operator | Kind::Rparen => {
-                                        // Do nothing.
-                                    }
-                                },
-                                _ => break Err("Syntax error.".to_string()),
-                            }
-                        }
-                        _ => break Err(format!("Could not find index {} and {}", 0, 2)),
+    // Filter returns the flat (ANDed) view of the expression.
+    // Returns None when the expression contains Or or Not.
+    pub fn filter(&self) -> Option<Filter> {
+        let mut predicates: Vec<Predicate> = vec![];
+        let mut pending: Vec<&Expr> = vec![self];
+        while let Some(expr) = pending.pop() {
+            match expr {
+                Expr::And(items) => pending.extend(items.iter().rev()),
+                Expr::Predicate(p) => predicates.push(p.clone()),
+                Expr::Or(_) | Expr::Not(_) => return None,
+            }
+        }
+        Some(Filter { predicates })
+    }
+}
+
+impl Expr {
+    // grouped returns the expression as a term.
+    // Groups of more than one item are wrapped in ().
+    fn grouped(&self) -> String {
+        match self {
+            Expr::And(items) | Expr::Or(items) if items.len() == 1 => items[0].grouped(),
+            Expr::And(_) | Expr::Or(_) => format!("({})", self),
+            Expr::Not(_) | Expr::Predicate(_) => self.to_string(),
+        }
+    }
+}
+
+// Display the canonical filter.
+// Parser::expr() of the result builds the same expression.
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::And(items) | Expr::Or(items) => {
+                let joint = if matches!(self, Expr::Or(_)) { OR } else { AND };
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", joint)?;
                     }
-                } else {
-                    brf.push(token.clone());
+                    write!(f, "{}", item.grouped())?;
                 }
-            } else {
-                break Ok(());
+                Ok(())
             }
-        }?;
-
-        if brf.is_empty() {
-            Ok(Filter { predicates })
-        } else {
-            Err("Syntax error.".to_string())
+            // `!!` scans as a single (unknown) operator.
+            Expr::Not(item) if matches!(**item, Expr::Not(_)) => write!(f, "{}({})", NOT, item),
+            Expr::Not(item) => write!(f, "{}{}", NOT, item.grouped()),
+            Expr::Predicate(p) => write!(f, "{}", p),
         }
     }
 }
 
+// Predicate field operator value.
+// The span covers the whole clause, including a list's parentheses.
+// A negated operator (`!=` `!~`) matches when the operator without
+// the `!` does not. For lists:
+//   status!=(closed|archived) -> status is neither closed nor archived.
+//   tags!=(a,b)               -> tags is not both a and b.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        into = "crate::serialize::PredicateJson",
+        try_from = "crate::serialize::PredicateJson"
+    )
+)]
 pub struct Predicate {
-    pub unused: Token,
     pub field: Token,
-    pub operator: Token,
+    pub operator: CompareOp,
     pub value: Value,
+    pub span: Span,
+}
+
+impl Predicate {
+    // Negated returns true when the operator is negated (`!=` `!~`).
+    pub fn negated(&self) -> bool {
+        self.operator.negated()
+    }
+}
+
+impl Display for Predicate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            self.field.field_query(),
+            self.operator,
+            self.value
+        )
+    }
 }
 
 // Value term value.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Value(pub Vec<Token>);
 
+// Display the value; lists are wrapped in ().
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let [token] = self.0.as_slice() {
+            return write!(f, "{}", token.query());
+        }
+        write!(f, "(")?;
+        for token in self.0.iter() {
+            write!(f, "{}", token.query())?;
+        }
+        write!(f, ")")
+    }
+}
+
 impl Value {
     // ByKind returns values by kind.
-    pub fn by_kind(&self, kind: Vec<Kind>) -> Vec<Token> {
-        let mut matched: Vec<Token> = vec![];
-        for t in self.0.iter() {
-            for k in kind.iter() {
-                if &t.kind == k {
-                    matched.push(t.clone());
-                }
-            }
-        }
-        matched
-    }
-
-    // pub fn operator(&self, operator: Kind) -> bool {
-    //     let operators = self.by_kind(vec![Kind::Operator]);
-    //     if !operators.is_empty() {
-    //         operators[0].kind == operator
-    //     } else {
-    //         false
-    //     }
-    // }
-
-    // pub fn join(&self, val: &[char]) -> Value {
-    //     let mut tokens: Vec<Token> = vec![];
-    //     for i in 0..self.by_kind(vec![Kind::Literal, Kind::String]).len() {
-    //         if i > 0 {
-    //             tokens.push(Token {
-    //                 kind: Kind::Operator,
-    //                 value: val.to_owned(),
-    //             });
-    //         }
-    //         tokens.push(self.0[i].clone());
-    //     }
-    //     Value(tokens)
-    // }
+    pub fn by_kind(&self, kind: &[Kind]) -> Vec<&Token> {
+        self.0.iter().filter(|t| kind.contains(&t.kind)).collect()
+    }
 }
 
-// List construct.
-// Example: (red|blue|green)
-pub struct List<'a> {
-    lexer: &'a mut Lexer,
+// Expression construct.
+// Terms are joined by `,` (And) or `|` (Or); mixing them requires ().
+// When flat, only `,` is accepted and terms cannot be negated.
+// Example: (name=jim|age>30),status=active
+// Counts predicates and nesting depth against the limits.
+pub struct Expression<'l, 'a> {
+    lexer: &'l mut Lexer<'a>,
+    flat: bool,
+    limits: &'l Limits,
+    predicates: usize,
+    depth: usize,
 }
 
-impl List<'_> {
-    // Build the value.
-    pub fn build(&mut self) -> Result<Value, String> {
-        let mut v = Value(vec![]);
+impl<'a> Expression<'_, 'a> {
+    // Build the expression.
+    // Stops at the end of the filter or before an unmatched `)`.
+    pub fn build(&mut self) -> Result<Expr, FilterError> {
+        let mut terms: Vec<Expr> = vec![];
+        let mut last_op: Option<char> = None;
 
         loop {
-            if let Some(token) = self.lexer.next() {
+            terms.push(self.term()?);
+            match self.lexer.peek() {
+                Some(Ok(token)) if token.kind == Kind::Rparen => break,
+                Some(_) => {}
+                None => break,
+            }
+            let token = self.separator()?;
+            let operator = if token.is(OR) { OR } else { AND };
+            if let Some(last_op) = last_op
+                && operator != last_op
+            {
+                return Err(FilterError::MixedOperators {
+                    expected: last_op,
+                    found: operator,
+                    span: token.span,
+                });
+            }
+            last_op = Some(operator);
+        }
+
+        match last_op {
+            Some(OR) => Ok(Expr::Or(terms)),
+            Some(_) => Ok(Expr::And(terms)),
+            None => Ok(terms.remove(0)),
+        }
+    }
+
+    // separator between terms.
+    fn separator(&mut self) -> Result<Lexeme<'a>, FilterError> {
+        let token = self.expect()?;
+        if token.kind == Kind::Operator && (token.is(AND) || (token.is(OR) && !self.flat)) {
+            Ok(token)
+        } else {
+            Err(unexpected(&token))
+        }
+    }
+
+    // term is a predicate, a group or a negated term.
+    fn term(&mut self) -> Result<Expr, FilterError> {
+        let token = self.expect()?;
+        match token.kind {
+            Kind::Operator if token.is(NOT) && !self.flat => {
+                let expr = self.nested(&token, |e| e.term())?;
+                Ok(Expr::Not(Box::new(expr)))
+            }
+            Kind::Lparen => {
+                let expr = self.nested(&token, |e| e.build())?;
+                match next(self.lexer, self.limits) {
+                    Some(Ok(rparen)) if rparen.kind == Kind::Rparen => Ok(expr),
+                    Some(Err(err)) => Err(err),
+                    _ => Err(FilterError::UnterminatedList {
+                        span: token.span.join(eof(self.lexer)),
+                    }),
+                }
+            }
+            Kind::Literal | Kind::String => {
+                let predicate = self.predicate(Token::from(&token))?;
+                self.predicates += 1;
+                self.limits
+                    .check(Limit::Predicates, self.predicates, predicate.span)?;
+                Ok(Expr::Predicate(predicate))
+            }
+            Kind::Operator | Kind::Rparen => Err(unexpected(&token)),
+        }
+    }
+
+    // nested builds the term one level deeper.
+    // The token (`(` `!`) is the span when too deep.
+    // The depth is capped by MAX_DEPTH even when unlimited.
+    fn nested(
+        &mut self,
+        token: &Lexeme,
+        build: impl FnOnce(&mut Self) -> Result<Expr, FilterError>,
+    ) -> Result<Expr, FilterError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(FilterError::LimitExceeded {
+                limit: Limit::Depth,
+                max: MAX_DEPTH,
+                span: token.span,
+            });
+        }
+        self.limits.check(Limit::Depth, self.depth, token.span)?;
+        let expr = build(self);
+        self.depth -= 1;
+        expr
+    }
+
+    // predicate following the field.
+    fn predicate(&mut self, field: Token) -> Result<Predicate, FilterError> {
+        let token = self.expect()?;
+        if token.kind != Kind::Operator || token.is(AND) || token.is(OR) {
+            return Err(unexpected(&token));
+        }
+        let operator = CompareOp::parse(&token.value).ok_or(FilterError::UnknownOperator {
+            operator: token.to_string(),
+            span: token.span,
+        })?;
+        let (value, span) = match self.lexer.peek() {
+            Some(Ok(token)) if token.kind == Kind::Lparen => {
+                let mut list = List {
+                    lexer: self.lexer,
+                    limits: self.limits,
+                    span: Span::default(),
+                };
+                let v = list.build()?;
+                (v, list.span)
+            }
+            _ => {
+                let token = self.expect()?;
                 match token.kind {
-                    Kind::Literal | Kind::String => v.0.push(token),
-                    Kind::Operator => {
-                        let value_string: String = token.value.clone().into_iter().collect();
-                        if value_string == AND.to_string() || value_string == OR.to_string() {
-                            v.0.push(token.clone());
-                        } else {
-                            break Err("List separator must be `,` `|`".to_string());
-                        }
-                    }
-                    Kind::Lparen => {
-                        // ignored
-                    }
-                    Kind::Rparen => {
-                        self.validate(&v)?;
-                        break Ok(v);
-                    }
+                    Kind::Literal | Kind::String => (Value(vec![Token::from(&token)]), token.span),
+                    _ => return Err(unexpected(&token)),
                 }
-            } else {
-                break Err("End ')' not found.".to_string());
             }
+        };
+        Ok(Predicate {
+            span: field.span.join(span),
+            field,
+            operator,
+            value,
+        })
+    }
+
+    // expect the next token.
+    fn expect(&mut self) -> Result<Lexeme<'a>, FilterError> {
+        match next(self.lexer, self.limits) {
+            Some(token) => token,
+            None => Err(FilterError::UnexpectedEnd {
+                span: eof(self.lexer),
+            }),
         }
     }
+}
+
+// List construct.
+// Items are joined by `,` or `|`, not both.
+// Example: (red|blue|green)
+// The span is set by build() and includes the parentheses.
+pub struct List<'l, 'a> {
+    lexer: &'l mut Lexer<'a>,
+    limits: &'l Limits,
+    pub span: Span,
+}
 
-    // validate the result.
-    pub fn validate(&mut self, v: &Value) -> Result<(), String> {
+impl<'a> List<'_, 'a> {
+    // Build the value.
+    pub fn build(&mut self) -> Result<Value, FilterError> {
+        let mut v = Value(vec![]);
         let mut last_op: Option<char> = None;
+        let mut items = 0;
+
+        let lparen = self.expect()?;
+        if lparen.kind != Kind::Lparen {
+            return Err(unexpected(&lparen));
+        }
+        self.span = lparen.span;
 
-        let mut i = 0;
         loop {
-            if v.0.is_empty() {
-                break Err("List cannot be empty.".to_string());
-            }
-            if i >= v.0.len() {
-                break Ok(());
+            let token = self.expect()?;
+            match token.kind {
+                Kind::Literal | Kind::String => {
+                    items += 1;
+                    self.limits.check(Limit::List, items, token.span)?;
+                    v.0.push(Token::from(&token));
+                }
+                Kind::Rparen if v.0.is_empty() => {
+                    return Err(FilterError::EmptyList {
+                        span: self.span.join(token.span),
+                    });
+                }
+                _ => return Err(unexpected(&token)),
             }
 
-            if let Some(token) = v.0.get(i) {
-                match (i % 2 == 0, &token.kind) {
-                    (true, Kind::Literal | Kind::String) => {
-                        // do nothing
-                    }
-                    (true, Kind::Operator | Kind::Lparen | Kind::Rparen) => {
-                        break Err("(LITERAL|STRING) not expected in ()".to_string());
-                    }
-                    (false, Kind::Operator) => {
-                        if let Some(operator) = token.value.first() {
-                            let operator = *operator;
-                            if let Some(last_op) = last_op {
-                                if operator != last_op {
-                                    break Err("Mixed operator detected in ().".to_string());
-                                }
-                            }
-                            last_op = Some(operator);
-                        } else {
-                            last_op = None
-                        }
-                    }
-                    (false, Kind::Literal | Kind::String | Kind::Lparen | Kind::Rparen) => {
-                        break Err("OPERATOR expected in ()".to_string());
+            let token = self.expect()?;
+            match token.kind {
+                Kind::Rparen => {
+                    self.span = self.span.join(token.span);
+                    return Ok(v);
+                }
+                Kind::Operator if token.is(AND) || token.is(OR) => {
+                    let operator = if token.is(OR) { OR } else { AND };
+                    if let Some(last_op) = last_op
+                        && operator != last_op
+                    {
+                        return Err(FilterError::MixedListOperators {
+                            expected: last_op,
+                            found: operator,
+                            span: token.span,
+                        });
                     }
-                };
-            } else {
-                break Err(format!("Expected element at index `{}`", i));
+                    last_op = Some(operator);
+                    v.0.push(Token::from(&token));
+                }
+                Kind::Operator => {
+                    return Err(FilterError::UnknownOperator {
+                        operator: token.to_string(),
+                        span: token.span,
+                    });
+                }
+                _ => return Err(unexpected(&token)),
             }
+        }
+    }
 
-            i += 1;
+    // expect the next token.
+    fn expect(&mut self) -> Result<Lexeme<'a>, FilterError> {
+        match next(self.lexer, self.limits) {
+            Some(token) => token,
+            None => Err(FilterError::UnterminatedList {
+                span: self.span.join(eof(self.lexer)),
+            }),
         }
     }
 }
@@ -208,7 +521,6 @@ impl List<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lexer::{COLON, EQ};
 
     #[test]
     fn test_parser_empty_string() {
@@ -224,40 +536,36 @@ mod tests {
             Ok(Filter {
                 predicates: vec![
                     Predicate {
-                        unused: Token {
-                            kind: Kind::Operator,
-                            value: vec![COMMA]
-                        },
                         field: Token {
                             kind: Kind::Literal,
-                            value: "name".chars().collect()
-                        },
-                        operator: Token {
-                            kind: Kind::Operator,
-                            value: vec![COLON]
+                            value: "name".chars().collect(),
+                            raw: None,
+                            span: Span::new(0, 4)
                         },
+                        operator: CompareOp::Has,
                         value: Value(vec![Token {
                             kind: Kind::Literal,
-                            value: "elmer".chars().collect()
+                            value: "elmer".chars().collect(),
+                            raw: None,
+                            span: Span::new(5, 10)
                         }]),
+                        span: Span::new(0, 10),
                     },
                     Predicate {
-                        unused: Token {
-                            kind: Kind::Operator,
-                            value: vec![COMMA]
-                        },
                         field: Token {
                             kind: Kind::Literal,
-                            value: "age".chars().collect()
-                        },
-                        operator: Token {
-                            kind: Kind::Operator,
-                            value: vec![COLON]
+                            value: "age".chars().collect(),
+                            raw: None,
+                            span: Span::new(11, 14)
                         },
+                        operator: CompareOp::Has,
                         value: Value(vec![Token {
                             kind: Kind::Literal,
-                            value: "20".chars().collect()
+                            value: "20".chars().collect(),
+                            raw: None,
+                            span: Span::new(15, 17)
                         }]),
+                        span: Span::new(11, 17),
                     }
                 ]
             })
@@ -272,76 +580,78 @@ mod tests {
             Ok(Filter {
                 predicates: vec![
                     Predicate {
-                        unused: Token {
-                            kind: Kind::Operator,
-                            value: vec![COMMA]
-                        },
                         field: Token {
                             kind: Kind::Literal,
-                            value: "name".chars().collect()
-                        },
-                        operator: Token {
-                            kind: Kind::Operator,
-                            value: vec![COLON]
+                            value: "name".chars().collect(),
+                            raw: None,
+                            span: Span::new(0, 4)
                         },
+                        operator: CompareOp::Has,
                         value: Value(vec![Token {
                             kind: Kind::Literal,
-                            value: "elmer".chars().collect()
+                            value: "elmer".chars().collect(),
+                            raw: None,
+                            span: Span::new(5, 10)
                         }]),
+                        span: Span::new(0, 10),
                     },
                     Predicate {
-                        unused: Token {
-                            kind: Kind::Operator,
-                            value: vec![COMMA]
-                        },
                         field: Token {
                             kind: Kind::Literal,
-                            value: "category".chars().collect()
-                        },
-                        operator: Token {
-                            kind: Kind::Operator,
-                            value: vec![EQ]
+                            value: "category".chars().collect(),
+                            raw: None,
+                            span: Span::new(11, 19)
                         },
+                        operator: CompareOp::Eq,
                         value: Value(vec![
                             Token {
                                 kind: Kind::Literal,
-                                value: "one".chars().collect()
+                                value: "one".chars().collect(),
+                                raw: None,
+                                span: Span::new(21, 24)
                             },
                             Token {
                                 kind: Kind::Operator,
-                                value: vec![OR]
+                                value: vec![OR],
+                                raw: None,
+                                span: Span::new(24, 25)
                             },
                             Token {
                                 kind: Kind::Literal,
-                                value: "two".chars().collect()
+                                value: "two".chars().collect(),
+                                raw: None,
+                                span: Span::new(25, 28)
                             },
                             Token {
                                 kind: Kind::Operator,
-                                value: vec![OR]
+                                value: vec![OR],
+                                raw: None,
+                                span: Span::new(28, 29)
                             },
                             Token {
                                 kind: Kind::Literal,
-                                value: "three".chars().collect()
+                                value: "three".chars().collect(),
+                                raw: None,
+                                span: Span::new(29, 34)
                             }
                         ]),
+                        span: Span::new(11, 35),
                     },
                     Predicate {
-                        unused: Token {
-                            kind: Kind::Operator,
-                            value: vec![COMMA]
-                        },
                         field: Token {
                             kind: Kind::Literal,
-                            value: "age".chars().collect()
-                        },
-                        operator: Token {
-                            kind: Kind::Operator,
-                            value: vec![COLON]
+                            value: "age".chars().collect(),
+                            raw: None,
+                            span: Span::new(36, 39)
                         },
+                        operator: CompareOp::Has,
                         value: Value(vec![Token {
                             kind: Kind::Literal,
-                            value: "20".chars().collect()
+                            value: "20".chars().collect(),
+                            raw: None,
+                            span: Span::new(40, 42)
                         }]),
+                        span: Span::new(36, 42),
                     },
                 ]
             })
@@ -359,4 +669,287 @@ mod tests {
         let p = Parser::filter("cat=(one|two,three)");
         assert!(p.is_err());
     }
+
+    #[test]
+    fn test_parser_expr_nested() {
+        let p = Parser::expr("(name=jim|age>30),status=active").unwrap();
+        let Expr::And(items) = &p else {
+            panic!("expected And: {:?}", p);
+        };
+        assert_eq!(items.len(), 2);
+        let Expr::Or(group) = &items[0] else {
+            panic!("expected Or: {:?}", items[0]);
+        };
+        assert_eq!(group.len(), 2);
+        assert!(matches!(&items[1], Expr::Predicate(p) if p.field.to_string() == "status"));
+        let fields: Vec<String> = p.predicates().iter().map(|p| p.field.to_string()).collect();
+        assert_eq!(fields, vec!["name", "age", "status"]);
+        assert_eq!(p.filter(), None);
+    }
+
+    #[test]
+    fn test_parser_expr_flat() {
+        let filter = "name:elmer,category=(one|two|three),(age:20,(id=1))";
+        let p = Parser::expr(filter).unwrap();
+        let flat = Parser::filter("name:elmer,category=(one|two|three),age:20,id=1").unwrap();
+        assert_eq!(p.filter().map(|f| f.predicates.len()), Some(4));
+        assert_eq!(
+            Parser::expr("name:elmer,age:20").unwrap().filter(),
+            Parser::filter("name:elmer,age:20").ok()
+        );
+        assert_eq!(flat.expr().filter(), Some(flat));
+    }
+
+    #[test]
+    fn test_parser_expr_single() {
+        let p = Parser::expr("((name=jim))").unwrap();
+        assert!(matches!(p, Expr::Predicate(_)));
+        let p = Parser::expr(" ").unwrap();
+        assert_eq!(p, Expr::And(vec![]));
+    }
+
+    #[test]
+    fn test_parser_expr_errors() {
+        assert_eq!(
+            Parser::expr("a=1|b=2,c=3"),
+            Err(FilterError::MixedOperators {
+                expected: OR,
+                found: AND,
+                span: Span::new(7, 8)
+            })
+        );
+        assert_eq!(
+            Parser::expr("(a=1|b=2"),
+            Err(FilterError::UnterminatedList {
+                span: Span::new(0, 8)
+            })
+        );
+        assert_eq!(
+            Parser::expr("a=1)"),
+            Err(FilterError::UnexpectedToken {
+                found: ")".to_string(),
+                span: Span::new(3, 4)
+            })
+        );
+        assert_eq!(
+            Parser::expr("a=1,"),
+            Err(FilterError::UnexpectedEnd {
+                span: Span::new(4, 4)
+            })
+        );
+        assert!(Parser::expr("a=1,()").is_err());
+        assert!(Parser::expr("a,b").is_err());
+    }
+
+    #[test]
+    fn test_parser_precise_errors() {
+        assert_eq!(
+            Parser::filter("a=b=c"),
+            Err(FilterError::UnexpectedToken {
+                found: "=".to_string(),
+                span: Span::new(3, 4)
+            })
+        );
+        assert_eq!(
+            Parser::filter("name=elmer,age="),
+            Err(FilterError::UnexpectedEnd {
+                span: Span::new(15, 15)
+            })
+        );
+        assert_eq!(
+            Parser::filter("name=,age=1"),
+            Err(FilterError::UnexpectedToken {
+                found: ",".to_string(),
+                span: Span::new(5, 6)
+            })
+        );
+        assert_eq!(
+            Parser::filter("cat=(one|)"),
+            Err(FilterError::UnexpectedToken {
+                found: ")".to_string(),
+                span: Span::new(9, 10)
+            })
+        );
+        assert_eq!(
+            Parser::filter("\""),
+            Err(FilterError::UnterminatedQuote {
+                quote: '"',
+                span: Span::new(0, 1)
+            })
+        );
+    }
+
+    #[test]
+    fn test_parser_flat() {
+        assert_eq!(
+            Parser::filter("a=1|b=2"),
+            Err(FilterError::UnexpectedToken {
+                found: "|".to_string(),
+                span: Span::new(3, 4)
+            })
+        );
+        let p = Parser::filter("(a=1,b=2),c=(x|y)").unwrap();
+        assert_eq!(p.predicates.len(), 3);
+    }
+
+    #[test]
+    fn test_parser_not() {
+        let p = Parser::expr("!(name=jim|age>30),status=active").unwrap();
+        let Expr::And(items) = &p else {
+            panic!("expected And: {:?}", p);
+        };
+        assert!(matches!(&items[0], Expr::Not(item) if matches!(**item, Expr::Or(_))));
+        assert!(matches!(&items[1], Expr::Predicate(p) if !p.negated()));
+
+        let p = Parser::expr("!name=jim").unwrap();
+        assert!(matches!(&p, Expr::Not(item) if matches!(**item, Expr::Predicate(_))));
+
+        let p = Parser::expr("status!=(closed|archived),name!~jim*").unwrap();
+        let predicates = p.predicates();
+        assert_eq!(predicates[0].operator, CompareOp::NotEq);
+        assert!(predicates[0].negated());
+        assert_eq!(predicates[1].operator, CompareOp::NotLike);
+        assert!(predicates[1].negated());
+    }
+
+    #[test]
+    fn test_parser_not_errors() {
+        assert_eq!(
+            Parser::expr("a=1,!"),
+            Err(FilterError::UnexpectedEnd {
+                span: Span::new(5, 5)
+            })
+        );
+        assert_eq!(
+            Parser::filter("!a=1"),
+            Err(FilterError::UnexpectedToken {
+                found: "!".to_string(),
+                span: Span::new(0, 1)
+            })
+        );
+        let p = Parser::filter("status!=(closed|archived)").unwrap();
+        assert!(p.predicates[0].negated());
+    }
+
+    #[test]
+    fn test_parser_limits() {
+        let limits = |f: fn(&mut Limits)| {
+            let mut limits = Limits::unlimited();
+            f(&mut limits);
+            limits
+        };
+        let exceeded =
+            |limit: Limit, max: usize, start: usize, end: usize| FilterError::LimitExceeded {
+                limit,
+                max,
+                span: Span::new(start, end),
+            };
+        assert_eq!(
+            Parser::filter_with("name=jim,age=1", &limits(|l| l.input = 8)).unwrap_err(),
+            exceeded(Limit::Input, 8, 8, 14)
+        );
+        assert_eq!(
+            Parser::filter_with("a=1,b=2,c=3", &limits(|l| l.predicates = 2)).unwrap_err(),
+            exceeded(Limit::Predicates, 2, 8, 11)
+        );
+        assert_eq!(
+            Parser::expr_with("(a=1|b=2),c=3", &limits(|l| l.predicates = 2)).unwrap_err(),
+            exceeded(Limit::Predicates, 2, 10, 13)
+        );
+        assert_eq!(
+            Parser::filter_with("a=(1|2|3)", &limits(|l| l.list = 2)).unwrap_err(),
+            exceeded(Limit::List, 2, 7, 8)
+        );
+        assert_eq!(
+            Parser::filter_with("name='elmer'", &limits(|l| l.token = 5)).unwrap_err(),
+            exceeded(Limit::Token, 5, 5, 12)
+        );
+        assert_eq!(
+            Parser::expr_with("!(!a=1)", &limits(|l| l.depth = 2)).unwrap_err(),
+            exceeded(Limit::Depth, 2, 2, 3)
+        );
+        assert!(Parser::expr_with("((a=1))", &limits(|l| l.depth = 2)).is_ok());
+
+        let filter = "name=(jim|crossley),age>=18,address='1 Main st'";
+        assert_eq!(
+            Parser::filter_with(filter, &Limits::default()),
+            Parser::filter(filter)
+        );
+        let deep = format!("{}a=1{}", "(".repeat(1000), ")".repeat(1000));
+        let err = Parser::expr_with(&deep, &Limits::default()).unwrap_err();
+        assert_eq!(err, exceeded(Limit::Depth, 16, 16, 17));
+        assert_eq!(
+            err.to_string(),
+            "nesting depth exceeds the limit of 16 (at 16..17)"
+        );
+        assert_eq!(Parser::expr(&deep).unwrap_err(), err);
+        assert_eq!(Parser::filter(&deep).unwrap_err(), err);
+        let deep = format!("{}a=1{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert!(matches!(
+            Parser::filter(&deep),
+            Err(FilterError::LimitExceeded { .. })
+        ));
+        assert!(matches!(
+            Parser::expr(&deep),
+            Err(FilterError::LimitExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parser_depth() {
+        let unlimited = Limits::unlimited();
+        let deep = format!("{}a=1{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert_eq!(
+            Parser::expr_with(&deep, &unlimited).unwrap_err(),
+            FilterError::LimitExceeded {
+                limit: Limit::Depth,
+                max: MAX_DEPTH,
+                span: Span::new(128, 129),
+            }
+        );
+        let deep = format!("{}a=1", "! ".repeat(10_000));
+        assert_eq!(
+            Parser::expr_with(&deep, &unlimited).unwrap_err(),
+            FilterError::LimitExceeded {
+                limit: Limit::Depth,
+                max: MAX_DEPTH,
+                span: Span::new(256, 257),
+            }
+        );
+        let deep = format!("{}a=1{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+        assert!(Parser::expr_with(&deep, &unlimited).is_ok());
+    }
+
+    #[test]
+    fn test_parser_display() {
+        let cases = [
+            ("", ""),
+            ("name=jim", "name=jim"),
+            (
+                "(name=jim | age>30), status=active",
+                "(name=jim|age>30),status=active",
+            ),
+            ("((a=1))", "a=1"),
+            ("!(a=1|b=2),!c=3", "!(a=1|b=2),!c=3"),
+            ("!(!a=1)", "!(!a=1)"),
+            ("a=1|(b=2|c=3)", "a=1|(b=2|c=3)"),
+            ("a='x,y'|b=(1,2)", "a='x,y'|b=(1,2)"),
+            ("a=\"it's\"", "a=\"it's\""),
+            ("a='!x'", "a='!x'"),
+            ("2024-05-01T10 :x", "2024-05-01T10:x"),
+            ("2024-05-01T10:x", "2024-05-01T10:x"),
+            ("2024-05-01T10\\:00=1", "2024-05-01T10\\:00=1"),
+            ("'2024-05-01T10:00'=1", "'2024-05-01T10:00'=1"),
+            ("at=2024-05-01T10:00", "at=2024-05-01T10:00"),
+            (
+                "at=(2024-05-01T10:00|2024-05-01T11:00)",
+                "at=(2024-05-01T10:00|2024-05-01T11:00)",
+            ),
+        ];
+        for (filter, expected) in cases {
+            let expr = Parser::expr(filter).unwrap();
+            assert_eq!(expr.to_string(), expected);
+            assert_eq!(Parser::expr(expected).unwrap().to_string(), expected);
+        }
+    }
 }
@@ -0,0 +1,368 @@
+use crate::datetime::{Date, DateTime, Duration};
+use crate::decimal::Decimal;
+use crate::filter::{Field, Filter};
+use crate::lexer::{Kind, OR, Token, TokenValue};
+use crate::operator::CompareOp;
+use crate::parser::{Expr, Predicate};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// Record values matched by filters.
+// Example:
+//   impl Record for User {
+//       fn get(&self, resource: Option<&str>, name: &str) -> Option<TokenValue> {
+//           match (resource, name) {
+//               (None, "name") => Some(TokenValue::String(self.name.clone())),
+//               (Some("app"), "name") => Some(TokenValue::String(self.app.clone())),
+//               _ => None,
+//           }
+//       }
+//   }
+pub trait Record {
+    // Get returns the value of the field.
+    // The resource is set for qualified fields (resource.name).
+    // Names are passed lowercase.
+    // Returns None when the record has no such field.
+    fn get(&self, resource: Option<&str>, name: &str) -> Option<TokenValue>;
+
+    // Values returns all values of a multi-valued field.
+    // A value matches when any of the values matches.
+    fn values(&self, resource: Option<&str>, name: &str) -> Vec<TokenValue> {
+        self.get(resource, name).into_iter().collect()
+    }
+
+    // Path returns all values of the field split on unescaped (.).
+    // Segments are passed as written. See: Field::path().
+    // The first segment is the resource of values(); names are lowercased.
+    fn path(&self, path: &[String]) -> Vec<TokenValue> {
+        let path: Vec<String> = path.iter().map(|s| s.to_lowercase()).collect();
+        match path.as_slice() {
+            [] => vec![],
+            [name] => self.values(None, name),
+            [resource, name @ ..] => self.values(Some(resource), &name.join(".")),
+        }
+    }
+}
+
+// Record keyed by field name (resource.name).
+// Keys are matched exactly, then case-insensitive. See: lookup().
+impl Record for HashMap<String, TokenValue> {
+    fn get(&self, resource: Option<&str>, name: &str) -> Option<TokenValue> {
+        let key = match resource {
+            Some(resource) => format!("{}.{}", resource, name),
+            None => name.to_string(),
+        };
+        lookup(HashMap::get(self, &key), self.iter(), &key).cloned()
+    }
+
+    fn path(&self, path: &[String]) -> Vec<TokenValue> {
+        let key = path.join(".");
+        lookup(HashMap::get(self, &key), self.iter(), &key)
+            .cloned()
+            .into_iter()
+            .collect()
+    }
+}
+
+// lookup returns the exact match, else the first entry whose key equals
+// the key case-insensitive (Unicode). Example: Ünit finds ünit.
+pub(crate) fn lookup<'m, V>(
+    exact: Option<&'m V>,
+    mut entries: impl Iterator<Item = (&'m String, &'m V)>,
+    key: &str,
+) -> Option<&'m V> {
+    let folded = |s: &str| s.chars().flat_map(char::to_lowercase).collect::<String>();
+    exact.or_else(|| {
+        let key = folded(key);
+        entries.find(|(k, _)| folded(k) == key).map(|(_, v)| v)
+    })
+}
+
+impl Filter {
+    // Matches returns true when the record matches all predicates.
+    pub fn matches(&self, record: &impl Record) -> bool {
+        self.predicates.iter().all(|p| p.matches(record))
+    }
+}
+
+impl Expr {
+    // Matches returns true when the record matches the expression.
+    pub fn matches(&self, record: &impl Record) -> bool {
+        match self {
+            Expr::And(items) => items.iter().all(|e| e.matches(record)),
+            Expr::Or(items) => items.iter().any(|e| e.matches(record)),
+            Expr::Not(item) => !item.matches(record),
+            Expr::Predicate(p) => p.matches(record),
+        }
+    }
+}
+
+impl Predicate {
+    // Matches returns true when the record matches the predicate.
+    // A list matches when any (|) or all (,) of the items match.
+    // Values are compared as the type of the record value; a value that
+    // is not valid for the type does not match.
+    // A missing field matches only negated operators.
+    pub fn matches(&self, record: &impl Record) -> bool {
+        let field = Field { predicate: self };
+        let values = record.path(&field.path());
+        let tokens = self.value.by_kind(&[Kind::Literal, Kind::String]);
+        let any = self
+            .value
+            .by_kind(&[Kind::Operator])
+            .first()
+            .map(|t| t.value.as_slice())
+            == Some(&[OR]);
+
+        let operator = if self.negated() {
+            self.operator.negate()
+        } else {
+            self.operator
+        };
+        let matched = |token: &Token| values.iter().any(|v| compare(v, operator, token));
+        let matched = if any {
+            tokens.into_iter().any(matched)
+        } else {
+            tokens.into_iter().all(matched)
+        };
+        matched != self.negated()
+    }
+}
+
+// compare the record value with the token.
+fn compare(value: &TokenValue, operator: CompareOp, token: &Token) -> bool {
+    let ordering = match operator {
+        CompareOp::Like => return like(&value.to_string(), &token.value),
+        CompareOp::NotLike => return !like(&value.to_string(), &token.value),
+        _ => ordering(value, &token.to_string()),
+    };
+    let Some(ordering) = ordering else {
+        return operator == CompareOp::NotEq;
+    };
+    match operator {
+        CompareOp::Eq | CompareOp::Has => ordering == Ordering::Equal,
+        CompareOp::NotEq => ordering != Ordering::Equal,
+        CompareOp::Lt => ordering == Ordering::Less,
+        CompareOp::Le => ordering != Ordering::Greater,
+        CompareOp::Gt => ordering == Ordering::Greater,
+        CompareOp::Ge => ordering != Ordering::Less,
+        CompareOp::Like | CompareOp::NotLike => unreachable!(),
+    }
+}
+
+// ordering of the record value and the token as the type of the value.
+// Returns None when the token is not valid for the type.
+fn ordering(value: &TokenValue, token: &str) -> Option<Ordering> {
+    match value {
+        TokenValue::String(v) => Some(v.as_str().cmp(token)),
+        TokenValue::Int(v) => Some(Decimal::parse(&v.to_string())?.cmp(&Decimal::parse(token)?)),
+        TokenValue::Decimal(v) => Some(v.cmp(&Decimal::parse(token)?)),
+        TokenValue::Float(v) => v.partial_cmp(&token.parse::<f64>().ok()?),
+        TokenValue::Bool(v) => Some(v.cmp(&token.parse::<bool>().ok()?)),
+        TokenValue::Date(v) => match Date::parse(token) {
+            Some(d) => Some(v.cmp(&d)),
+            None => Some(midnight(v).cmp(&DateTime::parse(token)?.timestamp())),
+        },
+        TokenValue::DateTime(v) => match Date::parse(token) {
+            Some(d) => Some(v.timestamp().cmp(&midnight(&d))),
+            None => Some(v.cmp(&DateTime::parse(token)?)),
+        },
+        TokenValue::Duration(v) => Some(v.cmp(&Duration::parse(token)?)),
+    }
+}
+
+// midnight (UTC) timestamp of the date.
+fn midnight(date: &Date) -> (i64, u32) {
+    (date.days() * 86400, 0)
+}
+
+// like returns true when the value matches the pattern.
+// The (*) wildcard matches any (or no) characters.
+fn like(value: &str, pattern: &[char]) -> bool {
+    let value: Vec<char> = value.chars().collect();
+    let (mut v, mut p) = (0, 0);
+    // position of the last (*) and the value it was matched at.
+    let mut star: Option<(usize, usize)> = None;
+    while v < value.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, v));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == value[v] {
+            p += 1;
+            v += 1;
+        } else if let Some((sp, sv)) = star {
+            p = sp + 1;
+            v = sv + 1;
+            star = Some((sp, sv + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    struct User {
+        name: String,
+        age: i64,
+        score: f64,
+        created: DateTime,
+        tags: Vec<String>,
+        app: String,
+    }
+
+    impl Record for User {
+        fn get(&self, resource: Option<&str>, name: &str) -> Option<TokenValue> {
+            match (resource, name) {
+                (None, "name") => Some(TokenValue::String(self.name.clone())),
+                (None, "age") => Some(TokenValue::Int(self.age)),
+                (None, "score") => Some(TokenValue::Float(self.score)),
+                (None, "created") => Some(TokenValue::DateTime(self.created)),
+                (Some("app"), "name") => Some(TokenValue::String(self.app.clone())),
+                _ => None,
+            }
+        }
+
+        fn values(&self, resource: Option<&str>, name: &str) -> Vec<TokenValue> {
+            match (resource, name) {
+                (None, "tags") => self
+                    .tags
+                    .iter()
+                    .map(|t| TokenValue::String(t.clone()))
+                    .collect(),
+                _ => self.get(resource, name).into_iter().collect(),
+            }
+        }
+    }
+
+    fn user() -> User {
+        User {
+            name: "elmer".to_string(),
+            age: 30,
+            score: 4.5,
+            created: DateTime::parse("2024-05-01T10:00:00Z").unwrap(),
+            tags: vec!["a".to_string(), "b".to_string()],
+            app: "hunting".to_string(),
+        }
+    }
+
+    fn matches(filter: &str) -> bool {
+        Parser::filter(filter).unwrap().matches(&user())
+    }
+
+    #[test]
+    fn test_matches_operators() {
+        assert!(matches("name=elmer"));
+        assert!(matches("name:elmer"));
+        assert!(!matches("name=fudd"));
+        assert!(matches("name!=fudd"));
+        assert!(matches("age=30,age>=30,age<=30,age>29,age<31,age!=31"));
+        assert!(!matches("age>30"));
+        assert!(matches("age<30.5"));
+        assert!(matches("score>4,score<4.6"));
+        assert!(!matches("age=x"));
+        assert!(matches("age!=x"));
+        assert!(matches("created>2024-05-01,created<2024-05-02"));
+        assert!(matches("created=2024-05-01T15:30:00+05:30"));
+        assert!(matches("app.name=hunting,App.name~hunt*"));
+        assert!(matches(""));
+    }
+
+    #[test]
+    fn test_matches_like() {
+        assert!(matches("name~elmer"));
+        assert!(matches("name~el*"));
+        assert!(matches("name~*er"));
+        assert!(matches("name~*l*e*"));
+        assert!(matches("name~*"));
+        assert!(!matches("name~el"));
+        assert!(!matches("name~*x*"));
+        assert!(matches("name!~*x*"));
+        assert!(!matches("name!~e*"));
+    }
+
+    #[test]
+    fn test_matches_list() {
+        assert!(matches("name=(fudd|elmer)"));
+        assert!(!matches("name=(fudd|bugs)"));
+        assert!(!matches("name=(elmer,fudd)"));
+        assert!(matches("name!=(fudd|bugs)"));
+        assert!(!matches("name!=(fudd|elmer)"));
+        assert!(matches("name!=(elmer,fudd)"));
+        assert!(matches("tags=(a,b)"));
+        assert!(!matches("tags=(a,c)"));
+        assert!(matches("tags=(c|b)"));
+        assert!(matches("tags!=(a,c)"));
+        assert!(matches("age>(40|20)"));
+        assert!(!matches("age>(40,20)"));
+    }
+
+    #[test]
+    fn test_matches_missing() {
+        assert!(!matches("zzz=1"));
+        assert!(matches("zzz!=1"));
+        assert!(!matches("tags.a=1"));
+    }
+
+    #[test]
+    fn test_matches_expr() {
+        let user = user();
+        let expr = |s: &str| Parser::expr(s).unwrap().matches(&user);
+        assert!(expr("name=fudd|age>18"));
+        assert!(!expr("name=fudd|age>40"));
+        assert!(expr("!(name=fudd),(age<18|tags:b)"));
+        assert!(!expr("!name=elmer"));
+    }
+
+    #[test]
+    fn test_matches_map() {
+        let record: HashMap<String, TokenValue> = HashMap::from([
+            ("name".to_string(), TokenValue::String("elmer".to_string())),
+            (
+                "App.Name".to_string(),
+                TokenValue::String("hunting".to_string()),
+            ),
+            (
+                "price".to_string(),
+                TokenValue::Decimal(Decimal::parse("10.50").unwrap()),
+            ),
+            ("active".to_string(), TokenValue::Bool(true)),
+            (
+                "age.max".to_string(),
+                TokenValue::Duration(Duration { seconds: 3600 }),
+            ),
+        ]);
+        let filter =
+            Parser::filter("Name=elmer,app.name=hunting,price>10.4999,active=true,age.max<1d")
+                .unwrap();
+        assert!(filter.matches(&record));
+        let filter = Parser::filter("price=10.5,active=false").unwrap();
+        assert!(!filter.matches(&record));
+
+        // keys are matched exactly, then case-insensitive.
+        let record: HashMap<String, TokenValue> = HashMap::from([
+            ("Name".to_string(), TokenValue::String("a".to_string())),
+            ("name".to_string(), TokenValue::String("b".to_string())),
+            ("Ünit".to_string(), TokenValue::Int(1)),
+        ]);
+        let matches = |filter: &str| Parser::filter(filter).unwrap().matches(&record);
+        assert!(matches("Name=a,name=b"));
+        assert!(!matches("Name=b"));
+        assert!(matches("Ünit=1,ünit=1,ÜNIT=1"));
+    }
+
+    #[test]
+    fn test_like() {
+        let p = |s: &str| s.chars().collect::<Vec<char>>();
+        assert!(like("", &p("")));
+        assert!(like("", &p("**")));
+        assert!(!like("", &p("a")));
+        assert!(like("abcabc", &p("*bc")));
+        assert!(like("abcabd", &p("a*c*d")));
+        assert!(!like("abcabd", &p("a*c*e")));
+    }
+}
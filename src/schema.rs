@@ -0,0 +1,436 @@
+use crate::datetime::{Date, DateTime, Duration};
+use crate::decimal::Decimal;
+use crate::error::FilterError;
+use crate::filter::Filter;
+use crate::lexer::{Kind, Token, TokenValue};
+use crate::operator::CompareOp;
+use crate::parser::{Expr, Predicate};
+use std::fmt::{Display, Formatter};
+
+// FieldType declared field type.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum FieldType {
+    String,
+    // Signed 64-bit.
+    Integer,
+    Float,
+    // Arbitrary precision.
+    Decimal,
+    Bool,
+    // RFC 3339 date or date and time.
+    Date,
+    // Amount of time. Example: 7d
+    Duration,
+    // One of the values.
+    Enum(Vec<String>),
+}
+
+impl FieldType {
+    // Operators returns the operators supported by default.
+    pub fn operators(&self) -> Vec<CompareOp> {
+        match self {
+            FieldType::String => vec![
+                CompareOp::Eq,
+                CompareOp::NotEq,
+                CompareOp::Has,
+                CompareOp::Like,
+                CompareOp::NotLike,
+            ],
+            FieldType::Integer
+            | FieldType::Float
+            | FieldType::Decimal
+            | FieldType::Date
+            | FieldType::Duration => vec![
+                CompareOp::Eq,
+                CompareOp::NotEq,
+                CompareOp::Has,
+                CompareOp::Lt,
+                CompareOp::Le,
+                CompareOp::Gt,
+                CompareOp::Ge,
+            ],
+            FieldType::Bool | FieldType::Enum(_) => {
+                vec![CompareOp::Eq, CompareOp::NotEq, CompareOp::Has]
+            }
+        }
+    }
+
+    // Coerce returns the value of the type.
+    // Returns None when the value is not valid for the type.
+    pub fn coerce(&self, value: &str) -> Option<TokenValue> {
+        match self {
+            FieldType::String => Some(TokenValue::String(value.to_string())),
+            FieldType::Integer => value.parse::<i64>().ok().map(TokenValue::Int),
+            FieldType::Float => value
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .map(TokenValue::Float),
+            FieldType::Decimal => Decimal::parse(value).map(TokenValue::Decimal),
+            FieldType::Bool => value.parse::<bool>().ok().map(TokenValue::Bool),
+            FieldType::Date => Date::parse(value)
+                .map(TokenValue::Date)
+                .or_else(|| DateTime::parse(value).map(TokenValue::DateTime)),
+            FieldType::Duration => Duration::parse(value).map(TokenValue::Duration),
+            FieldType::Enum(values) if values.iter().any(|v| v == value) => {
+                Some(TokenValue::String(value.to_string()))
+            }
+            FieldType::Enum(_) => None,
+        }
+    }
+
+    // Accepts returns true when the value is valid for the type.
+    pub fn accepts(&self, value: &str) -> bool {
+        self.coerce(value).is_some()
+    }
+}
+
+impl Display for FieldType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::String => write!(f, "string"),
+            FieldType::Integer => write!(f, "integer"),
+            FieldType::Float => write!(f, "float"),
+            FieldType::Decimal => write!(f, "decimal"),
+            FieldType::Bool => write!(f, "bool"),
+            FieldType::Date => write!(f, "date"),
+            FieldType::Duration => write!(f, "duration"),
+            FieldType::Enum(values) => write!(f, "one of: {}", values.join(", ")),
+        }
+    }
+}
+
+// SchemaField declared field.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        into = "crate::serialize::SchemaFieldJson",
+        from = "crate::serialize::SchemaFieldJson"
+    )
+)]
+pub struct SchemaField {
+    pub name: String,
+    pub kind: FieldType,
+    pub operators: Vec<CompareOp>,
+}
+
+// Schema allowed fields.
+// Field names are matched case-insensitive and may be
+// resource qualified (resource.name).
+// Example:
+//   Schema::new()
+//       .field("name", FieldType::String)
+//       .field_with("age", FieldType::Integer, &[CompareOp::Gt])
+// JSON (serde feature):
+//   {"fields": [
+//     {"name": "name", "type": "string"},
+//     {"name": "age", "type": "integer", "operators": [">"]},
+//     {"name": "status", "type": {"enum": ["open", "closed"]}}
+//   ]}
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Schema {
+    pub fields: Vec<SchemaField>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Field declares a field supporting the operators of the type.
+    pub fn field(self, name: &str, kind: FieldType) -> Self {
+        let operators = kind.operators();
+        self.field_with(name, kind, &operators)
+    }
+
+    // FieldWith declares a field supporting the operators.
+    pub fn field_with(mut self, name: &str, kind: FieldType, operators: &[CompareOp]) -> Self {
+        self.fields.push(SchemaField {
+            name: name.to_string(),
+            kind,
+            operators: operators.to_vec(),
+        });
+        self
+    }
+
+    // Get returns the declared field.
+    pub fn get(&self, name: &str) -> Option<&SchemaField> {
+        let name = name.to_lowercase();
+        self.fields.iter().find(|f| f.name.to_lowercase() == name)
+    }
+
+    // Validate returns all violations by the predicates.
+    pub fn validate<'a>(
+        &self,
+        predicates: impl IntoIterator<Item = &'a Predicate>,
+    ) -> Result<(), Vec<FilterError>> {
+        let mut violations: Vec<FilterError> = vec![];
+        for p in predicates {
+            violations.extend(self.violations(p));
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    // Check returns an error when the field of the predicate is not
+    // declared or does not support the operator.
+    pub fn check(&self, p: &Predicate) -> Result<(), FilterError> {
+        let field = self.declared(p)?;
+        supported(field, p)
+    }
+
+    // Coerce returns the value of the token for the declared field.
+    // Like patterns are always String.
+    pub fn coerce(&self, p: &Predicate, token: &Token) -> Result<TokenValue, FilterError> {
+        let field = self.declared(p)?;
+        let value = token.to_string();
+        if matches!(p.operator, CompareOp::Like | CompareOp::NotLike) {
+            return Ok(TokenValue::String(value));
+        }
+        field.kind.coerce(&value).ok_or(FilterError::InvalidValue {
+            field: field.name.clone(),
+            value,
+            expected: field.kind.to_string(),
+            span: token.span,
+        })
+    }
+
+    // declared field of the predicate.
+    fn declared(&self, p: &Predicate) -> Result<&SchemaField, FilterError> {
+        let name = p.field.to_string();
+        self.get(&name).ok_or(FilterError::UnknownField {
+            suggestion: self.suggest(&name),
+            name,
+            span: p.field.span,
+        })
+    }
+
+    // violations by the predicate.
+    fn violations(&self, p: &Predicate) -> Vec<FilterError> {
+        let field = match self.declared(p) {
+            Ok(field) => field,
+            Err(err) => return vec![err],
+        };
+
+        let mut violations: Vec<FilterError> = vec![];
+        if let Err(err) = supported(field, p) {
+            violations.push(err);
+        }
+        let values: Vec<&Token> = p.value.by_kind(&[Kind::Literal, Kind::String]);
+        for token in values.iter() {
+            if let Err(err) = self.coerce(p, token) {
+                violations.push(err);
+            }
+        }
+        violations
+    }
+
+    // suggest the closest declared field name.
+    fn suggest(&self, name: &str) -> Option<String> {
+        let name = name.to_lowercase();
+        let max = (name.chars().count() / 3).max(2);
+        self.fields
+            .iter()
+            .map(|f| (distance(&name, &f.name.to_lowercase()), &f.name))
+            .filter(|(d, _)| *d <= max)
+            .min_by_key(|(d, _)| *d)
+            .map(|(_, name)| name.clone())
+    }
+}
+
+impl Filter {
+    // Validate returns all violations of the schema.
+    pub fn validate(&self, schema: &Schema) -> Result<(), Vec<FilterError>> {
+        schema.validate(self.predicates.iter())
+    }
+}
+
+impl Expr {
+    // Validate returns all violations of the schema.
+    pub fn validate(&self, schema: &Schema) -> Result<(), Vec<FilterError>> {
+        schema.validate(self.predicates())
+    }
+}
+
+// supported returns an error when the field does not support the operator.
+fn supported(field: &SchemaField, p: &Predicate) -> Result<(), FilterError> {
+    if field.operators.contains(&p.operator) {
+        Ok(())
+    } else {
+        Err(FilterError::UnsupportedOperator {
+            field: field.name.clone(),
+            operator: p.operator,
+            span: p.span,
+        })
+    }
+}
+
+// distance (Levenshtein) between the strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            let cost = if ca == *cb { 0 } else { 1 };
+            row[j + 1] = (previous + cost).min(row[j] + 1).min(current + 1);
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Span;
+    use crate::parser::Parser;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .field("name", FieldType::String)
+            .field("name.first", FieldType::String)
+            .field("age", FieldType::Integer)
+            .field("score", FieldType::Float)
+            .field("price", FieldType::Decimal)
+            .field("active", FieldType::Bool)
+            .field("created", FieldType::Date)
+            .field(
+                "status",
+                FieldType::Enum(vec!["open".to_string(), "closed".to_string()]),
+            )
+            .field_with("vulnerabilities", FieldType::Integer, &[CompareOp::Gt])
+    }
+
+    #[test]
+    fn test_schema_valid() {
+        let p = Parser::filter(
+            "name~jim*,Name.First=elmer,age>=18,active=true,created>2024-01-31,status=(open|closed),vulnerabilities>10",
+        )
+        .unwrap();
+        assert_eq!(p.validate(&schema()), Ok(()));
+    }
+
+    #[test]
+    fn test_schema_violations() {
+        let p =
+            Parser::filter("nmae=jim,age~1,age=(1|x),status=new,vulnerabilities=1,zzz=1").unwrap();
+        assert_eq!(
+            p.validate(&schema()),
+            Err(vec![
+                FilterError::UnknownField {
+                    name: "nmae".to_string(),
+                    suggestion: Some("name".to_string()),
+                    span: Span::new(0, 4)
+                },
+                FilterError::UnsupportedOperator {
+                    field: "age".to_string(),
+                    operator: CompareOp::Like,
+                    span: Span::new(9, 14)
+                },
+                FilterError::InvalidValue {
+                    field: "age".to_string(),
+                    value: "x".to_string(),
+                    expected: "integer".to_string(),
+                    span: Span::new(22, 23)
+                },
+                FilterError::InvalidValue {
+                    field: "status".to_string(),
+                    value: "new".to_string(),
+                    expected: "one of: open, closed".to_string(),
+                    span: Span::new(32, 35)
+                },
+                FilterError::UnsupportedOperator {
+                    field: "vulnerabilities".to_string(),
+                    operator: CompareOp::Eq,
+                    span: Span::new(36, 53)
+                },
+                FilterError::UnknownField {
+                    name: "zzz".to_string(),
+                    suggestion: None,
+                    span: Span::new(54, 57)
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_schema_expr() {
+        let p = Parser::expr("!(name=jim|agee>1)").unwrap();
+        let violations = p.validate(&schema()).unwrap_err();
+        assert_eq!(
+            violations[0].message(),
+            "unknown field `agee`, did you mean `age`?"
+        );
+    }
+
+    #[test]
+    fn test_schema_coerce() {
+        let schema = schema();
+        let p = Parser::filter("age>-1,score>=4.5,price<99999999999999999999.99,name=20,age='7'")
+            .unwrap();
+        let values: Vec<TokenValue> = p
+            .predicates
+            .iter()
+            .map(|p| schema.coerce(p, &p.value.0[0]).unwrap())
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                TokenValue::Int(-1),
+                TokenValue::Float(4.5),
+                TokenValue::Decimal(Decimal::parse("99999999999999999999.99").unwrap()),
+                TokenValue::String("20".to_string()),
+                TokenValue::Int(7),
+            ]
+        );
+
+        let p = Parser::filter("age=99999999999999999999,score=inf").unwrap();
+        let violations = p.validate(&schema).unwrap_err();
+        assert_eq!(
+            violations[0].message(),
+            "invalid value `99999999999999999999` for field `age`, expected integer"
+        );
+        assert_eq!(
+            violations[1].message(),
+            "invalid value `inf` for field `score`, expected float"
+        );
+    }
+
+    #[test]
+    fn test_schema_date() {
+        assert_eq!(
+            FieldType::Date.coerce("2024-01-31"),
+            Some(TokenValue::Date(Date::parse("2024-01-31").unwrap()))
+        );
+        assert_eq!(
+            FieldType::Date.coerce("2024-01-31T10:00:00Z"),
+            Some(TokenValue::DateTime(
+                DateTime::parse("2024-01-31T10:00:00Z").unwrap()
+            ))
+        );
+        assert!(!FieldType::Date.accepts("2024-13-01"));
+        assert!(!FieldType::Date.accepts("2024-1-01"));
+        assert!(!FieldType::Date.accepts("2024-01-31T10"));
+        assert!(!FieldType::Date.accepts("yesterday"));
+        assert_eq!(
+            FieldType::Duration.coerce("1h30m"),
+            Some(TokenValue::Duration(Duration { seconds: 5400 }))
+        );
+        assert!(!FieldType::Duration.accepts("7"));
+
+        let schema = schema().field("age.max", FieldType::Duration);
+        let p = Parser::filter("created>=2024-05-01T10:00:00Z,age.max<7d").unwrap();
+        assert_eq!(p.validate(&schema), Ok(()));
+    }
+}
@@ -0,0 +1,481 @@
+use crate::builder;
+use crate::datetime::{Date, DateTime, Duration};
+use crate::decimal::Decimal;
+use crate::error::Span;
+use crate::lexer::{AND, Kind, OR, Token, TokenValue, escape};
+use crate::operator::CompareOp;
+use crate::parser::{Expr, Predicate, Value};
+use crate::schema::{FieldType, SchemaField};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Display;
+
+// PredicateJson stable JSON of a predicate.
+// The value is a scalar, {"any": [..]} (|) or {"all": [..]} (,).
+// Example:
+//   {"field":"age","op":">=","value":18}
+//   {"field":"label","op":"=","value":{"any":["a","b"]}}
+//   {"field":"q","op":"=","value":{"string":"18"}}
+// Spans are not serialized.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PredicateJson {
+    field: String,
+    op: CompareOp,
+    value: ValueJson,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ValueJson {
+    List(ListJson),
+    Scalar(Scalar),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ListJson {
+    Any(Vec<Scalar>),
+    All(Vec<Scalar>),
+}
+
+// Scalar JSON value.
+// Literals are JSON numbers and bools when written exactly as one,
+// else the literal as a JSON string. Quoted strings are {"string": ".."}.
+// Example: 18, 20.0 => 18, "20.0"; '18' => {"string":"18"}
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    Literal(String),
+    String { string: String },
+}
+
+impl From<&Token> for Scalar {
+    fn from(token: &Token) -> Self {
+        let text = token.to_string();
+        if token.kind != Kind::Literal {
+            return Scalar::String { string: text };
+        }
+        // numbers and bools only when written the same way.
+        let same = |value: &dyn Display| value.to_string() == text;
+        if let Ok(b) = text.parse::<bool>() {
+            Scalar::Bool(b)
+        } else if let Ok(n) = text.parse::<i64>()
+            && same(&n)
+        {
+            Scalar::Int(n)
+        } else if let Ok(n) = text.parse::<u64>()
+            && same(&n)
+        {
+            Scalar::Uint(n)
+        } else if let TokenValue::Float(f) = token.as_value()
+            && same(&f)
+        {
+            Scalar::Float(f)
+        } else {
+            Scalar::Literal(text)
+        }
+    }
+}
+
+impl From<Scalar> for Token {
+    fn from(scalar: Scalar) -> Self {
+        let (kind, value) = match scalar {
+            Scalar::Bool(b) => (Kind::Literal, b.to_string()),
+            Scalar::Int(n) => (Kind::Literal, n.to_string()),
+            Scalar::Uint(n) => (Kind::Literal, n.to_string()),
+            Scalar::Float(f) => (Kind::Literal, f.to_string()),
+            Scalar::Literal(s) => (Kind::Literal, s),
+            Scalar::String { string } => (Kind::String, string),
+        };
+        let value: Vec<char> = value.chars().collect();
+        // raw as written; see Token::query().
+        let raw = match kind {
+            Kind::Literal => Token::unless_value(escape(&value).chars().collect(), &value),
+            _ => None,
+        };
+        Token {
+            kind,
+            value,
+            raw,
+            span: Span::default(),
+        }
+    }
+}
+
+impl From<Predicate> for PredicateJson {
+    fn from(p: Predicate) -> Self {
+        let items: Vec<Scalar> = p
+            .value
+            .by_kind(&[Kind::Literal, Kind::String])
+            .into_iter()
+            .map(Scalar::from)
+            .collect();
+        let separator = p.value.by_kind(&[Kind::Operator]).first().copied();
+        let value = match separator {
+            Some(token) if token.value == [OR] => ValueJson::List(ListJson::Any(items)),
+            Some(_) => ValueJson::List(ListJson::All(items)),
+            None => match items.into_iter().next() {
+                Some(scalar) => ValueJson::Scalar(scalar),
+                None => ValueJson::List(ListJson::Any(vec![])),
+            },
+        };
+        PredicateJson {
+            field: p.field.to_string(),
+            op: p.operator,
+            value,
+        }
+    }
+}
+
+impl TryFrom<PredicateJson> for Predicate {
+    type Error = String;
+
+    fn try_from(json: PredicateJson) -> Result<Self, Self::Error> {
+        let (separator, items) = match json.value {
+            ValueJson::Scalar(scalar) => (None, vec![scalar]),
+            ValueJson::List(ListJson::Any(items)) => (Some(OR), items),
+            ValueJson::List(ListJson::All(items)) => (Some(AND), items),
+        };
+        if items.is_empty() {
+            return Err(format!("empty list for field `{}`", json.field));
+        }
+        let mut tokens: Vec<Token> = vec![];
+        for (i, scalar) in items.into_iter().enumerate() {
+            if let Some(separator) = separator
+                && i > 0
+            {
+                tokens.push(Token {
+                    kind: Kind::Operator,
+                    value: vec![separator],
+                    raw: None,
+                    span: Span::default(),
+                });
+            }
+            tokens.push(Token::from(scalar));
+        }
+        Ok(Predicate {
+            field: builder::field_token(&json.field),
+            operator: json.op,
+            value: Value(tokens),
+            span: Span::default(),
+        })
+    }
+}
+
+// ExprJson JSON of an expression.
+// Empty groups are rejected but for the top-level And.
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ExprJson {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    #[serde(untagged)]
+    Predicate(Predicate),
+}
+
+impl TryFrom<ExprJson> for Expr {
+    type Error = String;
+
+    fn try_from(json: ExprJson) -> Result<Self, Self::Error> {
+        let empty = |e: &Expr| matches!(e, Expr::And(items) | Expr::Or(items) if items.is_empty());
+        let expr = match json {
+            ExprJson::And(items) => Expr::And(items),
+            ExprJson::Or(items) => Expr::Or(items),
+            ExprJson::Not(item) => Expr::Not(item),
+            ExprJson::Predicate(p) => Expr::Predicate(p),
+        };
+        let valid = match &expr {
+            Expr::Or(items) if items.is_empty() => false,
+            Expr::And(items) | Expr::Or(items) => !items.iter().any(empty),
+            Expr::Not(item) => !empty(item),
+            Expr::Predicate(_) => true,
+        };
+        if valid {
+            Ok(expr)
+        } else {
+            Err("empty group".to_string())
+        }
+    }
+}
+
+// SchemaFieldJson JSON of a schema field.
+// Operators are omitted when those of the type.
+// Example: {"name":"age","type":"integer","operators":[">",">="]}
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SchemaFieldJson {
+    name: String,
+    #[serde(rename = "type")]
+    kind: FieldType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    operators: Option<Vec<CompareOp>>,
+}
+
+impl From<SchemaField> for SchemaFieldJson {
+    fn from(field: SchemaField) -> Self {
+        let operators = Some(field.operators).filter(|ops| *ops != field.kind.operators());
+        SchemaFieldJson {
+            name: field.name,
+            kind: field.kind,
+            operators,
+        }
+    }
+}
+
+impl From<SchemaFieldJson> for SchemaField {
+    fn from(json: SchemaFieldJson) -> Self {
+        SchemaField {
+            operators: json.operators.unwrap_or_else(|| json.kind.operators()),
+            name: json.name,
+            kind: json.kind,
+        }
+    }
+}
+
+impl Serialize for CompareOp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for CompareOp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        CompareOp::parse(&s).ok_or_else(|| D::Error::custom(format!("unknown operator `{}`", s)))
+    }
+}
+
+// text serializes the value as its Display string.
+fn text<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+// parsed deserializes the value from a string.
+fn parsed<'de, T, D>(deserializer: D, parse: fn(&str) -> Option<T>) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse(&s).ok_or_else(|| D::Error::custom(format!("invalid value `{}`", s)))
+}
+
+// Serialize and Deserialize as strings.
+macro_rules! textual {
+    ($($t:ty),*) => {$(
+        impl Serialize for $t {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                text(self, serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $t {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                parsed(deserializer, <$t>::parse)
+            }
+        }
+    )*};
+}
+
+textual!(Decimal, Date, DateTime, Duration);
+
+// Chars token value as a string.
+pub(crate) mod chars_option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Vec<char>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => chars::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<char>>, D::Error> {
+        let s = Option::<String>::deserialize(deserializer)?;
+        Ok(s.map(|s| s.chars().collect()))
+    }
+}
+
+pub(crate) mod chars {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &[char], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&value.iter().collect::<String>())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<char>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(s.chars().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::Filter;
+    use crate::lexer::{Kind, Token, TokenValue};
+    use crate::operator::CompareOp;
+    use crate::parser::{Expr, Parser};
+    use crate::schema::{FieldType, Schema};
+    use serde_json::json;
+
+    #[test]
+    fn test_serde_filter() {
+        let query = "age>=18,name=jim,active=true,score<4.5,label=(a|b),tags!=('x y',z),q='18',price=20.0,id=007,at=2024-01-01";
+        let filter = Parser::filter(query).unwrap();
+        let value = serde_json::to_value(&filter).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                {"field": "age", "op": ">=", "value": 18},
+                {"field": "name", "op": "=", "value": "jim"},
+                {"field": "active", "op": "=", "value": true},
+                {"field": "score", "op": "<", "value": 4.5},
+                {"field": "label", "op": "=", "value": {"any": ["a", "b"]}},
+                {"field": "tags", "op": "!=", "value": {"all": [{"string": "x y"}, "z"]}},
+                {"field": "q", "op": "=", "value": {"string": "18"}},
+                {"field": "price", "op": "=", "value": "20.0"},
+                {"field": "id", "op": "=", "value": "007"},
+                {"field": "at", "op": "=", "value": "2024-01-01"}
+            ])
+        );
+
+        let decoded: Filter = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded.to_string(), query);
+        let kinds = |f: &Filter| -> Vec<(Kind, TokenValue)> {
+            f.predicates
+                .iter()
+                .flat_map(|p| p.value.0.iter().map(|t| (t.kind, t.as_value())))
+                .collect()
+        };
+        assert_eq!(kinds(&decoded), kinds(&filter));
+    }
+
+    #[test]
+    fn test_serde_expr() {
+        let expr = Parser::expr("!(name=jim|age>30),created>2024-01-01").unwrap();
+        let value = serde_json::to_value(&expr).unwrap();
+        assert_eq!(
+            value,
+            json!({"and": [
+                {"not": {"or": [
+                    {"field": "name", "op": "=", "value": "jim"},
+                    {"field": "age", "op": ">", "value": 30}
+                ]}},
+                {"field": "created", "op": ">", "value": "2024-01-01"}
+            ]})
+        );
+        let decoded: Expr = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded.to_string(), expr.to_string());
+    }
+
+    #[test]
+    fn test_serde_errors() {
+        let result = serde_json::from_value::<Filter>(json!([
+            {"field": "age", "op": "=>", "value": 1}
+        ]));
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("unknown operator `=>`")
+        );
+        let result = serde_json::from_value::<Filter>(json!([
+            {"field": "age", "op": "=", "value": {"any": []}}
+        ]));
+        assert!(result.unwrap_err().to_string().contains("empty list"));
+
+        let p = json!({"field": "a", "op": "=", "value": 1});
+        for value in [
+            json!({"or": []}),
+            json!({"not": {"and": []}}),
+            json!({"and": [p, {"and": []}]}),
+            json!({"or": [p, {"not": {"or": []}}]}),
+        ] {
+            let result = serde_json::from_value::<Expr>(value.clone());
+            assert!(result.is_err(), "{}", value);
+        }
+        let expr = serde_json::from_value::<Expr>(json!({"not": {"or": [p]}})).unwrap();
+        assert_eq!(expr.to_string(), "!a=1");
+        let result = serde_json::from_value::<Expr>(json!({"or": []}));
+        assert_eq!(result.unwrap_err().to_string(), "empty group");
+        let expr: Expr = serde_json::from_value(json!({"and": []})).unwrap();
+        assert_eq!(expr, Expr::And(vec![]));
+        assert_eq!(Parser::expr(&expr.to_string()).unwrap(), expr);
+    }
+
+    #[test]
+    fn test_serde_schema() {
+        let schema = Schema::new()
+            .field("name", FieldType::String)
+            .field_with("age", FieldType::Integer, &[CompareOp::Gt])
+            .field(
+                "status",
+                FieldType::Enum(vec!["open".to_string(), "closed".to_string()]),
+            );
+        let value = serde_json::to_value(&schema).unwrap();
+        assert_eq!(
+            value,
+            json!({"fields": [
+                {"name": "name", "type": "string"},
+                {"name": "age", "type": "integer", "operators": [">"]},
+                {"name": "status", "type": {"enum": ["open", "closed"]}}
+            ]})
+        );
+        assert_eq!(serde_json::from_value::<Schema>(value).unwrap(), schema);
+        let result = serde_json::from_value::<Schema>(json!({"fields": [
+            {"name": "age", "type": "int"}
+        ]}));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_serde_token() {
+        let token = Token {
+            kind: Kind::String,
+            value: "x y".chars().collect(),
+            raw: None,
+            span: crate::error::Span::new(4, 9),
+        };
+        let value = serde_json::to_value(&token).unwrap();
+        assert_eq!(
+            value,
+            json!({"kind": "String", "value": "x y", "span": {"start": 4, "end": 9}})
+        );
+        assert_eq!(serde_json::from_value::<Token>(value).unwrap(), token);
+        let value =
+            json!({"kind": "String", "value": "x y", "raw": "x y", "span": {"start": 4, "end": 9}});
+        assert_eq!(serde_json::from_value::<Token>(value).unwrap(), token);
+        let token = Token {
+            kind: Kind::Literal,
+            value: "a,b".chars().collect(),
+            raw: Some(r"a\,b".chars().collect()),
+            span: crate::error::Span::new(0, 4),
+        };
+        let value = serde_json::to_value(&token).unwrap();
+        assert_eq!(value["raw"], r"a\,b");
+        assert_eq!(serde_json::from_value::<Token>(value).unwrap(), token);
+
+        let values = vec![
+            TokenValue::Int(1),
+            TokenValue::Decimal(crate::decimal::Decimal::parse("1.50").unwrap()),
+            TokenValue::Duration(crate::datetime::Duration { seconds: 60 }),
+        ];
+        let value = serde_json::to_value(&values).unwrap();
+        assert_eq!(
+            value,
+            json!([{"Int": 1}, {"Decimal": "1.5"}, {"Duration": "1m"}])
+        );
+        assert_eq!(
+            serde_json::from_value::<Vec<TokenValue>>(value).unwrap(),
+            values
+        );
+    }
+}
@@ -0,0 +1,474 @@
+use crate::error::FilterError;
+use crate::filter::{Field, Filter};
+use crate::lexer::{Kind, OR, Token, TokenValue};
+use crate::operator::CompareOp;
+use crate::parser::{Expr, Predicate};
+use crate::schema::Schema;
+use std::collections::HashSet;
+
+// LIKE escape character.
+// Not (\): MySQL reads ESCAPE '\' as an unterminated string.
+const ESCAPE: char = '!';
+
+// Placeholder bind parameter style.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Placeholder {
+    // `?` (SQLite, MySQL)
+    Question,
+    // `$1` (Postgres)
+    Numbered,
+    // `:name` named after the column.
+    Named,
+}
+
+// Bind parameter.
+// The name is the placeholder as it appears in the clause.
+#[derive(Debug, PartialEq)]
+pub struct Bind {
+    pub name: String,
+    pub value: TokenValue,
+}
+
+// Where parameterized WHERE clause.
+// The clause is "" when the filter is empty.
+#[derive(Debug, PartialEq)]
+pub struct Where {
+    pub clause: String,
+    pub binds: Vec<Bind>,
+}
+
+impl Where {
+    // Values returns the bind values in order.
+    pub fn values(&self) -> Vec<&TokenValue> {
+        self.binds.iter().map(|b| &b.value).collect()
+    }
+}
+
+// Sql WHERE clause generator.
+// Values are never written into the clause; only bind placeholders.
+// Fields must be identifiers (`name` or `resource.name`).
+// With a schema, bind values are coerced to the declared field type;
+// otherwise the type is guessed from the literal.
+//
+// Mapping:
+//   name=a        -> name = ?
+//   name:a        -> name = ?
+//   name!=a       -> name <> ?
+//   name~a*       -> name LIKE ? ESCAPE '!' (a%)
+//   name!~a*      -> name NOT LIKE ? ESCAPE '!'
+//   name=(a|b)    -> name IN (?, ?)
+//   name!=(a|b)   -> name NOT IN (?, ?)
+//   name>(a|b)    -> (name > ? OR name > ?)
+//   name=(a,b)    -> (name = ? AND name = ?)
+//   name!=(a,b)   -> NOT (name = ? AND name = ?)
+pub struct Sql {
+    pub placeholder: Placeholder,
+    pub schema: Option<Schema>,
+}
+
+impl Sql {
+    pub fn new(placeholder: Placeholder) -> Self {
+        Self {
+            placeholder,
+            schema: None,
+        }
+    }
+
+    // Schema sets the schema used to type bind values.
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    // Filter builds the WHERE clause for the filter.
+    pub fn filter(&self, filter: &Filter) -> Result<Where, FilterError> {
+        self.expr(&filter.expr())
+    }
+
+    // Expr builds the WHERE clause for the expression.
+    pub fn expr(&self, expr: &Expr) -> Result<Where, FilterError> {
+        let mut builder = Builder {
+            placeholder: self.placeholder,
+            schema: self.schema.as_ref(),
+            binds: vec![],
+            names: HashSet::new(),
+        };
+        let clause = match expr {
+            Expr::And(items) if items.is_empty() => String::new(),
+            _ => builder.expr(expr, true)?,
+        };
+        Ok(Where {
+            clause,
+            binds: builder.binds,
+        })
+    }
+}
+
+// Builder clause builder.
+struct Builder<'a> {
+    placeholder: Placeholder,
+    schema: Option<&'a Schema>,
+    binds: Vec<Bind>,
+    // named placeholders in use.
+    names: HashSet<String>,
+}
+
+impl Builder<'_> {
+    // expr clause.
+    // Nested clauses are enclosed in ().
+    fn expr(&mut self, expr: &Expr, top: bool) -> Result<String, FilterError> {
+        let (items, joint) = match expr {
+            Expr::And(items) if items.is_empty() => return Ok("1 = 1".to_string()),
+            Expr::Or(items) if items.is_empty() => return Ok("1 = 0".to_string()),
+            Expr::And(items) => (items, " AND "),
+            Expr::Or(items) => (items, " OR "),
+            Expr::Not(item) => return Ok(format!("NOT ({})", self.expr(item, true)?)),
+            Expr::Predicate(p) => return self.predicate(p),
+        };
+        let mut clauses: Vec<String> = vec![];
+        for item in items.iter() {
+            clauses.push(self.expr(item, false)?);
+        }
+        let clause = clauses.join(joint);
+        if top || items.len() == 1 {
+            Ok(clause)
+        } else {
+            Ok(format!("({})", clause))
+        }
+    }
+
+    // predicate clause.
+    // With a schema, the field and operator are checked first;
+    // like patterns are not coerced.
+    fn predicate(&mut self, p: &Predicate) -> Result<String, FilterError> {
+        let field = Field { predicate: p };
+        let column = column(&field)?;
+        if let Some(schema) = self.schema {
+            schema.check(p)?;
+        }
+        let values = p.value.by_kind(&[Kind::Literal, Kind::String]);
+        let any = p
+            .value
+            .by_kind(&[Kind::Operator])
+            .first()
+            .map(|t| t.value.as_slice())
+            == Some(&[OR]);
+
+        if values.len() == 1 {
+            return self.compare(&column, p, p.operator, values[0]);
+        }
+
+        if any {
+            match p.operator {
+                CompareOp::Eq | CompareOp::Has | CompareOp::NotEq => {
+                    let mut placeholders: Vec<String> = vec![];
+                    for token in values.iter() {
+                        let value = self.value(p, token)?;
+                        placeholders.push(self.bind(&field.name(), value));
+                    }
+                    let not = if p.negated() { "NOT " } else { "" };
+                    return Ok(format!(
+                        "{} {}IN ({})",
+                        column,
+                        not,
+                        placeholders.join(", ")
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        let operator = if p.negated() {
+            p.operator.negate()
+        } else {
+            p.operator
+        };
+        let mut clauses: Vec<String> = vec![];
+        for token in values.iter() {
+            clauses.push(self.compare(&column, p, operator, token)?);
+        }
+        let joint = if any { " OR " } else { " AND " };
+        let clause = format!("({})", clauses.join(joint));
+        if p.negated() {
+            Ok(format!("NOT {}", clause))
+        } else {
+            Ok(clause)
+        }
+    }
+
+    // compare column with a single value.
+    fn compare(
+        &mut self,
+        column: &str,
+        p: &Predicate,
+        operator: CompareOp,
+        token: &Token,
+    ) -> Result<String, FilterError> {
+        let field = Field { predicate: p };
+        match operator {
+            CompareOp::Like | CompareOp::NotLike => {
+                let value: String = token.to_string();
+                let placeholder = self.bind(&field.name(), TokenValue::String(like(&value)));
+                let not = if operator.negated() { "NOT " } else { "" };
+                Ok(format!(
+                    "{} {}LIKE {} ESCAPE '{}'",
+                    column, not, placeholder, ESCAPE
+                ))
+            }
+            _ => {
+                let value = self.value(p, token)?;
+                let placeholder = self.bind(&field.name(), value);
+                let operator = match operator {
+                    CompareOp::Has => "=",
+                    CompareOp::NotEq => "<>",
+                    _ => operator.as_str(),
+                };
+                Ok(format!("{} {} {}", column, operator, placeholder))
+            }
+        }
+    }
+
+    // value of the token.
+    fn value(&self, p: &Predicate, token: &Token) -> Result<TokenValue, FilterError> {
+        match self.schema {
+            Some(schema) => schema.coerce(p, token),
+            None => Ok(token.as_value()),
+        }
+    }
+
+    // bind the value and return the placeholder.
+    fn bind(&mut self, name: &str, value: TokenValue) -> String {
+        let placeholder = match self.placeholder {
+            Placeholder::Question => "?".to_string(),
+            Placeholder::Numbered => format!("${}", self.binds.len() + 1),
+            // suffixed until unique; a field may be named like a suffixed one.
+            Placeholder::Named => {
+                let name = name.to_lowercase();
+                let mut placeholder = format!(":{}", name);
+                let mut n = 1;
+                while self.names.contains(&placeholder) {
+                    n += 1;
+                    placeholder = format!(":{}_{}", name, n);
+                }
+                self.names.insert(placeholder.clone());
+                placeholder
+            }
+        };
+        self.binds.push(Bind {
+            name: placeholder.clone(),
+            value,
+        });
+        placeholder
+    }
+}
+
+// column returns the (validated) column for the field.
+// The resource and name must be identifiers.
+fn column(field: &Field) -> Result<String, FilterError> {
+    let (resource, name) = field.split();
+    let valid = identifier(&name) && resource.as_deref().is_none_or(identifier);
+    if !valid {
+        return Err(FilterError::InvalidField {
+            name: field.predicate.field.to_string(),
+            span: field.predicate.field.span,
+        });
+    }
+    match resource {
+        Some(resource) => Ok(format!("{}.{}", resource, name)),
+        None => Ok(name),
+    }
+}
+
+// identifier returns true when the name is a plain SQL identifier.
+fn identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(ch) if ch.is_ascii_alphabetic() || ch == '_' => {
+            chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+        }
+        _ => false,
+    }
+}
+
+// like returns the LIKE pattern.
+// The (*) wildcard is mapped to (%); (%), (_) and (!) are escaped.
+fn like(value: &str) -> String {
+    let mut pattern = String::new();
+    for ch in value.chars() {
+        match ch {
+            '*' => pattern.push('%'),
+            '%' | '_' | ESCAPE => {
+                pattern.push(ESCAPE);
+                pattern.push(ch);
+            }
+            _ => pattern.push(ch),
+        }
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Span;
+    use crate::parser::Parser;
+    use crate::schema::FieldType;
+
+    fn sql(filter: &str, placeholder: Placeholder) -> Result<Where, FilterError> {
+        let filter = Parser::filter(filter)?;
+        Sql::new(placeholder).filter(&filter)
+    }
+
+    #[test]
+    fn test_sql_empty() {
+        let w = sql("", Placeholder::Question).unwrap();
+        assert_eq!(w.clause, "");
+        assert!(w.binds.is_empty());
+    }
+
+    #[test]
+    fn test_sql_question() {
+        let w = sql(
+            "name:elmer,age>=18,app.tag!=x,category=(a|b|c)",
+            Placeholder::Question,
+        )
+        .unwrap();
+        assert_eq!(
+            w.clause,
+            "name = ? AND age >= ? AND app.tag <> ? AND category IN (?, ?, ?)"
+        );
+        assert_eq!(
+            w.values(),
+            vec![
+                &TokenValue::String("elmer".to_string()),
+                &TokenValue::Int(18),
+                &TokenValue::String("x".to_string()),
+                &TokenValue::String("a".to_string()),
+                &TokenValue::String("b".to_string()),
+                &TokenValue::String("c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sql_numbered() {
+        let w = sql(
+            "name~'el*_100%',status!=(closed|archived)",
+            Placeholder::Numbered,
+        )
+        .unwrap();
+        assert_eq!(
+            w.clause,
+            "name LIKE $1 ESCAPE '!' AND status NOT IN ($2, $3)"
+        );
+        assert_eq!(
+            w.binds[0].value,
+            TokenValue::String("el%!_100!%".to_string())
+        );
+        assert_eq!(w.binds[2].name, "$3");
+        assert_eq!(like(r"a!b\c*"), r"a!!b\c%");
+    }
+
+    #[test]
+    fn test_sql_named() {
+        let w = sql("age>1,age<(5|9),name!~x*", Placeholder::Named).unwrap();
+        assert_eq!(
+            w.clause,
+            "age > :age AND (age < :age_2 OR age < :age_3) AND name NOT LIKE :name ESCAPE '!'"
+        );
+        let names: Vec<&str> = w.binds.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec![":age", ":age_2", ":age_3", ":name"]);
+
+        let w = sql("age=1,age=2,age_2=3", Placeholder::Named).unwrap();
+        assert_eq!(w.clause, "age = :age AND age = :age_2 AND age_2 = :age_2_2");
+        let names: Vec<&str> = w.binds.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec![":age", ":age_2", ":age_2_2"]);
+    }
+
+    #[test]
+    fn test_sql_lists() {
+        let w = sql("tag=(a,b),tag!=(c,d),tag!~(x*|y*)", Placeholder::Question).unwrap();
+        assert_eq!(
+            w.clause,
+            "(tag = ? AND tag = ?) AND NOT (tag = ? AND tag = ?) AND NOT (tag LIKE ? ESCAPE '!' OR tag LIKE ? ESCAPE '!')"
+        );
+    }
+
+    #[test]
+    fn test_sql_expr() {
+        let expr = Parser::expr("!(name=jim|age>30),status=active").unwrap();
+        let w = Sql::new(Placeholder::Numbered).expr(&expr).unwrap();
+        assert_eq!(w.clause, "NOT (name = $1 OR age > $2) AND status = $3");
+    }
+
+    #[test]
+    fn test_sql_schema() {
+        let schema = Schema::new()
+            .field("name", FieldType::String)
+            .field("age", FieldType::Integer)
+            .field("score", FieldType::Float);
+        let filter = Parser::filter("name=20,age=(1|'2'),score>=4.5").unwrap();
+        let w = Sql::new(Placeholder::Question)
+            .schema(schema.clone())
+            .filter(&filter)
+            .unwrap();
+        assert_eq!(
+            w.values(),
+            vec![
+                &TokenValue::String("20".to_string()),
+                &TokenValue::Int(1),
+                &TokenValue::Int(2),
+                &TokenValue::Float(4.5),
+            ]
+        );
+
+        let filter = Parser::filter("age=x").unwrap();
+        let err = Sql::new(Placeholder::Question)
+            .schema(schema)
+            .filter(&filter)
+            .unwrap_err();
+        assert_eq!(
+            err.message(),
+            "invalid value `x` for field `age`, expected integer"
+        );
+
+        let schema = Schema::new().field("name", FieldType::String).field_with(
+            "age",
+            FieldType::Integer,
+            &[CompareOp::Eq],
+        );
+        let sql = Sql::new(Placeholder::Question).schema(schema);
+        for filter in ["zzz=1", "zzz~a*", "nme!~a*"] {
+            let err = sql.filter(&Parser::filter(filter).unwrap()).unwrap_err();
+            assert!(
+                matches!(err, FilterError::UnknownField { .. }),
+                "{}: {:?}",
+                filter,
+                err
+            );
+        }
+        let err = sql.filter(&Parser::filter("age~1*").unwrap()).unwrap_err();
+        assert_eq!(err.message(), "operator `~` not supported by field `age`");
+        assert!(sql.filter(&Parser::filter("name~a*").unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_sql_injection() {
+        let w = sql("name=\"x' OR 1=1 --\"", Placeholder::Question).unwrap();
+        assert_eq!(w.clause, "name = ?");
+        assert_eq!(
+            w.values(),
+            vec![&TokenValue::String("x' OR 1=1 --".to_string())]
+        );
+        assert_eq!(
+            sql("'name; DROP TABLE x'=1", Placeholder::Question),
+            Err(FilterError::InvalidField {
+                name: "name; DROP TABLE x".to_string(),
+                span: Span::new(0, 20)
+            })
+        );
+        assert!(sql("1name=1", Placeholder::Question).is_err());
+        assert!(sql("name\\.x=1", Placeholder::Question).is_err());
+        assert!(sql("app.tag.id=1", Placeholder::Question).is_err());
+    }
+}
@@ -0,0 +1,319 @@
+use crate::error::{FilterError, Span};
+use crate::filter::Filter;
+use crate::limits::Limits;
+use crate::parser::{Expr, Parser};
+use std::fmt::Display;
+
+// Characters not encoded in addition to the unreserved (A-Z a-z 0-9 - . _ ~).
+// Keeps filters readable. Example: name=(jim|crossley) => name%3D(jim%7Ccrossley)
+const SAFE: &[u8] = b"!'()*,:";
+
+// Decode percent-encoded text; (+) is a space.
+// Spans of errors refer to the encoded text.
+pub fn decode(text: &str) -> Result<String, FilterError> {
+    let input = text.as_bytes();
+    let mut bytes: Vec<u8> = vec![];
+    // offset in the input of each decoded byte.
+    let mut offsets: Vec<usize> = vec![];
+    let mut i = 0;
+    while i < input.len() {
+        offsets.push(i);
+        match input[i] {
+            b'+' => {
+                bytes.push(b' ');
+                i += 1;
+            }
+            b'%' => {
+                let hex = input
+                    .get(i + 1..i + 3)
+                    .and_then(|h| std::str::from_utf8(h).ok());
+                // from_str_radix() accepts a sign. Example: %+A
+                let hex = hex.filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()));
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(b) => {
+                        bytes.push(b);
+                        i += 3;
+                    }
+                    None => {
+                        let end = text.ceil_char_boundary((i + 3).min(text.len()));
+                        return Err(FilterError::InvalidEncoding {
+                            sequence: text[i..end].to_string(),
+                            span: Span::new(i, end),
+                        });
+                    }
+                }
+            }
+            b => {
+                bytes.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).map_err(|err| {
+        let start = offsets[err.utf8_error().valid_up_to()];
+        let end = err
+            .utf8_error()
+            .error_len()
+            .and_then(|len| offsets.get(err.utf8_error().valid_up_to() + len))
+            .copied()
+            .unwrap_or(text.len());
+        FilterError::InvalidEncoding {
+            sequence: text[start..end].to_string(),
+            span: Span::new(start, end),
+        }
+    })
+}
+
+// Encode the text for a query parameter value.
+// Spaces are encoded as (+).
+pub fn encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for b in text.bytes() {
+        match b {
+            b' ' => encoded.push('+'),
+            b if b.is_ascii_alphanumeric() || b"-._~".contains(&b) || SAFE.contains(&b) => {
+                encoded.push(b as char)
+            }
+            b => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+// Param returns the decoded value of the named parameter.
+// The query may start with (?). Example: ?filter=name%3Djim&sort=name
+// Returns the first value; None when not found. Pairs whose key is not
+// valid are skipped; spans of errors refer to the query.
+pub fn param(query: &str, name: &str) -> Result<Option<String>, FilterError> {
+    let mut offset = query.len() - query.strip_prefix('?').unwrap_or(query).len();
+    for pair in query[offset..].split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if decode(key).is_ok_and(|key| key == name) {
+            let start = offset + pair.len() - value.len();
+            return decode(value).map(Some).map_err(|err| match err {
+                FilterError::InvalidEncoding { sequence, span } => FilterError::InvalidEncoding {
+                    sequence,
+                    span: Span::new(span.start + start, span.end + start),
+                },
+                err => err,
+            });
+        }
+        offset += pair.len() + 1;
+    }
+    Ok(None)
+}
+
+// Filter parses the named parameter of the query within the default limits.
+// A missing parameter is an empty filter.
+// Spans of encoding errors refer to the query, of parser errors to the
+// decoded filter.
+pub fn filter(query: &str, name: &str) -> Result<Filter, FilterError> {
+    filter_with(query, name, &Limits::default())
+}
+
+// Expr parses the named parameter of the query within the default limits.
+// A missing parameter is an empty expression.
+pub fn expr(query: &str, name: &str) -> Result<Expr, FilterError> {
+    expr_with(query, name, &Limits::default())
+}
+
+// FilterWith parses the named parameter of the query within the limits.
+pub fn filter_with(query: &str, name: &str, limits: &Limits) -> Result<Filter, FilterError> {
+    let filter = param(query, name)?.unwrap_or_default();
+    Parser::filter_with(&filter, limits)
+}
+
+// ExprWith parses the named parameter of the query within the limits.
+pub fn expr_with(query: &str, name: &str, limits: &Limits) -> Result<Expr, FilterError> {
+    let filter = param(query, name)?.unwrap_or_default();
+    Parser::expr_with(&filter, limits)
+}
+
+// Query returns the name=value query parameter.
+// Example: query("filter", &filter) => filter=name%3D(jim%7Ccrossley)
+pub fn query(name: &str, filter: &impl Display) -> String {
+    format!("{}={}", encode(name), encode(&filter.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::FilterBuilder;
+    use crate::limits::Limit;
+
+    #[test]
+    fn test_url_decode() {
+        assert_eq!(
+            decode("name%3D(jim%7Ccrossley)").unwrap(),
+            "name=(jim|crossley)"
+        );
+        assert_eq!(decode("a+b%20c%2B").unwrap(), "a b c+");
+        assert_eq!(decode("%C3%A9t%c3%a9").unwrap(), "été");
+        assert_eq!(decode("").unwrap(), "");
+        assert_eq!(
+            decode("a%3").unwrap_err(),
+            FilterError::InvalidEncoding {
+                sequence: "%3".to_string(),
+                span: Span::new(1, 3)
+            }
+        );
+        assert_eq!(
+            decode("a%zzb").unwrap_err(),
+            FilterError::InvalidEncoding {
+                sequence: "%zz".to_string(),
+                span: Span::new(1, 4)
+            }
+        );
+        assert_eq!(
+            decode("a%+Ab").unwrap_err(),
+            FilterError::InvalidEncoding {
+                sequence: "%+A".to_string(),
+                span: Span::new(1, 4)
+            }
+        );
+        assert_eq!(
+            decode("%-1").unwrap_err(),
+            FilterError::InvalidEncoding {
+                sequence: "%-1".to_string(),
+                span: Span::new(0, 3)
+            }
+        );
+        assert_eq!(
+            decode("ok%FFx").unwrap_err(),
+            FilterError::InvalidEncoding {
+                sequence: "%FF".to_string(),
+                span: Span::new(2, 5)
+            }
+        );
+    }
+
+    #[test]
+    fn test_url_encode() {
+        assert_eq!(encode("name=(jim|crossley)"), "name%3D(jim%7Ccrossley)");
+        assert_eq!(encode("a b&c+d%"), "a+b%26c%2Bd%25");
+        assert_eq!(encode("été"), "%C3%A9t%C3%A9");
+        let text = "q~'a b',x=\"1&2\"|été";
+        assert_eq!(decode(&encode(text)).unwrap(), text);
+    }
+
+    #[test]
+    fn test_url_filter() {
+        let query = "?sort=name&filter=name%3D(jim%7Ccrossley),age%3E%3D18+&page=2";
+        let filter = filter(query, "filter").unwrap();
+        assert_eq!(filter.to_string(), "name=(jim|crossley),age>=18");
+        assert_eq!(
+            super::filter(query, "q").unwrap(),
+            Filter { predicates: vec![] }
+        );
+        assert_eq!(param(query, "page").unwrap(), Some("2".to_string()));
+        assert_eq!(param("flag&x=1", "flag").unwrap(), Some("".to_string()));
+        assert_eq!(
+            param("b%zz=1&filter=a%3D1", "filter").unwrap(),
+            Some("a=1".to_string())
+        );
+        assert_eq!(
+            param("?sort=name&filter=a%zz", "filter").unwrap_err(),
+            FilterError::InvalidEncoding {
+                sequence: "%zz".to_string(),
+                span: Span::new(19, 22)
+            }
+        );
+        assert_eq!(
+            param("sort=name&filter=a%zz", "filter").unwrap_err(),
+            FilterError::InvalidEncoding {
+                sequence: "%zz".to_string(),
+                span: Span::new(18, 21)
+            }
+        );
+        assert_eq!(
+            param("sort=%zz&page=2", "page").unwrap(),
+            Some("2".to_string())
+        );
+
+        let expr = expr("q=!(a%3D1%7Cb%3D2)", "q").unwrap();
+        assert_eq!(expr.to_string(), "!(a=1|b=2)");
+
+        let err = super::filter("filter=name%3D'x", "filter").unwrap_err();
+        assert_eq!(
+            err,
+            FilterError::UnterminatedQuote {
+                quote: '\'',
+                span: Span::new(5, 7)
+            }
+        );
+    }
+
+    #[test]
+    fn test_url_limits() {
+        let query = format!("q={}a%3D1", "(".repeat(100));
+        let err = expr(&query, "q").unwrap_err();
+        assert!(matches!(
+            err,
+            FilterError::LimitExceeded {
+                limit: Limit::Depth,
+                max: 16,
+                ..
+            }
+        ));
+        let err = super::filter(&query, "q").unwrap_err();
+        assert!(matches!(
+            err,
+            FilterError::LimitExceeded {
+                limit: Limit::Depth,
+                ..
+            }
+        ));
+        let query = format!("q={}a%3D1", "(".repeat(10000));
+        let err = expr(&query, "q").unwrap_err();
+        assert!(matches!(
+            err,
+            FilterError::LimitExceeded {
+                limit: Limit::Input,
+                ..
+            }
+        ));
+
+        let limits = Limits {
+            predicates: 1,
+            ..Limits::default()
+        };
+        assert!(filter_with("q=a%3D1", "q", &limits).is_ok());
+        let err = filter_with("q=a%3D1,b%3D2", "q", &limits).unwrap_err();
+        assert!(matches!(
+            err,
+            FilterError::LimitExceeded {
+                limit: Limit::Predicates,
+                max: 1,
+                ..
+            }
+        ));
+        let err = expr_with("q=a%3D1%7Cb%3D2", "q", &limits).unwrap_err();
+        assert!(matches!(
+            err,
+            FilterError::LimitExceeded {
+                limit: Limit::Predicates,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_url_query() {
+        let filter = FilterBuilder::new()
+            .any_of("name", ["jim", "crossley"])
+            .eq("address", "1 Main st & co")
+            .build()
+            .unwrap();
+        let query = query("filter", &filter);
+        assert_eq!(
+            query,
+            "filter=name%3D(jim%7Ccrossley),address%3D'1+Main+st+%26+co'"
+        );
+        assert_eq!(
+            super::filter(&format!("?{}&page=2", query), "filter")
+                .unwrap()
+                .to_string(),
+            filter.to_string()
+        );
+    }
+}